@@ -0,0 +1,46 @@
+//! Compiles a large generated champion and reports how fast the compiler goes,
+//! `cargo bench -p compiler --bench compile`.
+
+extern crate compiler;
+
+use std::fmt::Write;
+use std::time::Instant;
+use compiler::compile;
+
+const BLOCKS: usize = 2_000;
+const INSTRUCTIONS_PER_BLOCK: usize = 8;
+const RUNS: usize = 20;
+
+/// Generates blocks of instructions referencing labels before and after them.
+fn source() -> String {
+    let mut source = String::from(".name \"generated\"\n.comment \"a lot of labels\"\n\n");
+    for block in 0..BLOCKS {
+        let (previous, next) = (block.saturating_sub(1), (block + 1) % BLOCKS);
+        writeln!(source, "block{}:\tlive %{}", block, block).unwrap();
+        writeln!(source, "\t\tld %{}, r2", block * 4).unwrap();
+        writeln!(source, "\t\tsti r1, %:block{}, %1", next).unwrap();
+        writeln!(source, "\t\tadd r2, r3, r4").unwrap();
+        writeln!(source, "\t\tand r2, %-1, r5").unwrap();
+        writeln!(source, "\t\tldi %:block{}, r2, r6", previous).unwrap();
+        writeln!(source, "\t\tfork %:block{}", previous).unwrap();
+        writeln!(source, "\t\tzjmp %:block{}", next).unwrap();
+    }
+    source
+}
+
+fn main() {
+    let source = source();
+    let instructions = BLOCKS * INSTRUCTIONS_PER_BLOCK;
+
+    let start = Instant::now();
+    let mut bytes = 0;
+    for _ in 0..RUNS {
+        bytes = compile(&source).unwrap().len();
+    }
+    let secs = start.elapsed().as_secs_f64();
+
+    println!("compile ({} runs): {} lines, {} instructions, {} bytes each",
+             RUNS, source.lines().count(), instructions, bytes);
+    println!("    {:.3}s, {:.0} instructions/s, {:.0} source bytes/s",
+             secs, (RUNS * instructions) as f64 / secs, (RUNS * source.len()) as f64 / secs);
+}
@@ -1,8 +1,4 @@
-#![feature(try_from)]
-#![feature(const_fn)]
-#![feature(const_size_of)]
-
-#[macro_use] extern crate log;
+extern crate log;
 extern crate pest;
 #[macro_use] extern crate pest_derive;
 pub extern crate core;
@@ -13,9 +9,6 @@ mod property;
 mod label;
 
 use std::rc::Rc;
-use std::io::Write;
-use std::borrow::Cow;
-use std::mem;
 use std::collections::HashMap;
 use std::convert::TryFrom;
 use pest::{Parser, Error};
@@ -23,14 +16,14 @@ use pest::inputs::{StringInput, Span};
 use pest::iterators::Pair;
 use machine::instruction::mem_size::MemSize;
 use machine::instruction::Instruction;
-use core::{Header, COREWAR_EXEC_MAGIC, PROG_NAME_LENGTH, COMMENT_LENGTH};
+use core::{Header, HeaderError, HEADER_SIZE, PROG_NAME_LENGTH, COMMENT_LENGTH};
 use var_instr::variable::LabelNotFound;
 use var_instr::VarInstr;
 use property::Property;
 use label::Label;
 
 // force recompilation
-const _GRAMMAR: &'static str = include_str!("asm.pest");
+const _GRAMMAR: &str = include_str!("asm.pest");
 
 #[derive(Parser)]
 #[grammar = "asm.pest"]
@@ -50,8 +43,11 @@ pub fn compile(input: &str) -> Result<Vec<u8>, AsmError> {
     let parsed_program = parse_program(input)?;
     let (name, comment, instrs) = destruct_program(&parsed_program)?;
 
-    let mut output = Vec::with_capacity(mem::size_of::<Header>());
-    raw_compile(&name, &comment, &instrs, &mut output);
+    let mut output = Vec::with_capacity(HEADER_SIZE);
+    raw_compile(&name, &comment, &instrs, &mut output).map_err(|e| Error::CustomErrorPos {
+        message: e.to_string(),
+        pos: parsed_program.file_pair.clone().into_span().start_pos(),
+    })?;
     Ok(output)
 }
 
@@ -108,7 +104,7 @@ pub fn parse_program(input: &str) -> Result<ParsedProgram, AsmError> {
 }
 
 pub fn destruct_program(parsed_program: &ParsedProgram) -> Result<(String, String, Vec<Instruction>), AsmError> {
-    let &ParsedProgram { ref file_pair, ref properties, ref instructions } = parsed_program;
+    let ParsedProgram { file_pair, properties, instructions } = parsed_program;
 
     let name = match properties.get("name") {
         Some(&(_, Some(ref value))) if value.as_str().is_empty() => return Err(Error::CustomErrorSpan {
@@ -117,14 +113,10 @@ pub fn destruct_program(parsed_program: &ParsedProgram) -> Result<(String, Strin
         }),
         Some(&(_, Some(ref value))) => {
             let max_name_len = PROG_NAME_LENGTH;
-            let value_len = value.as_str().as_bytes().len();
-            let len = if max_name_len < value_len {
+            if max_name_len < value.as_str().len() {
                 eprintln!("name property's value as been clamped to {} chars.", max_name_len);
-                max_name_len
-            } else { value_len };
-
-            let value_bytes = &value.as_str().as_bytes()[..len];
-            String::from_utf8_lossy(value_bytes)
+            }
+            clamp_str(value.as_str(), max_name_len)
         },
         Some(&(ref span, None)) => return Err(Error::CustomErrorPos {
             message: "name property need a value".into(),
@@ -139,36 +131,71 @@ pub fn destruct_program(parsed_program: &ParsedProgram) -> Result<(String, Strin
     let comment = match properties.get("comment") {
         Some(&(_, Some(ref value))) => {
             let max_comment_len = COMMENT_LENGTH;
-            let value_len = value.as_str().as_bytes().len();
-            let len = if max_comment_len < value_len {
+            if max_comment_len < value.as_str().len() {
                 eprintln!("comment property's value as been clamped to {} chars.", max_comment_len);
-                max_comment_len
-            } else { value_len };
-
-            let value_bytes = &value.as_str().as_bytes()[..len];
-            String::from_utf8_lossy(value_bytes)
+            }
+            clamp_str(value.as_str(), max_comment_len)
         },
-        _ => Cow::Borrowed(""),
+        _ => "",
     };
 
     Ok((name.into(), comment.into(), instructions.clone()))
 }
 
-pub fn raw_compile(name: &str, comment: &str, instrs: &[Instruction], output: &mut Vec<u8>) {
-    let mut header = Header {
-        magic: COREWAR_EXEC_MAGIC.to_be(),
-        prog_name: [0u8; PROG_NAME_LENGTH + 1],
-        prog_size: (instrs.iter().map(MemSize::mem_size).sum::<usize>() as u32).to_be(),
-        comment: [0u8; COMMENT_LENGTH + 1],
-    };
+/// Returns the longest prefix of `s` that fits in `max_len` bytes
+/// without splitting a character.
+fn clamp_str(s: &str, max_len: usize) -> &str {
+    let mut len = s.len().min(max_len);
+    while !s.is_char_boundary(len) { len -= 1 }
+    &s[..len]
+}
 
-    (&mut header.prog_name[..]).write_all(name.as_bytes()).unwrap();
-    (&mut header.comment[..]).write_all(comment.as_bytes()).unwrap();
+pub fn raw_compile(name: &str, comment: &str, instrs: &[Instruction], output: &mut Vec<u8>) -> Result<(), HeaderError> {
+    let header = Header {
+        prog_name: name.to_string(),
+        prog_size: instrs.iter().map(MemSize::mem_size).sum::<usize>() as u32,
+        comment: comment.to_string(),
+    };
 
-    let header: [u8; mem::size_of::<Header>()] = unsafe { mem::transmute(header) };
-    output.write_all(&header).unwrap();
+    header.write_to(output)?;
 
     for instr in instrs {
-        instr.write_to(output).unwrap();
+        instr.write_to(output)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use machine::champion::Champion;
+
+    const ZORK: &str = ".name \"zork\"
+.comment \"I'M ALIIIIVE\"
+
+l2:     sti r1, %:live, %1
+        and r1, %0, r1
+live:   live %1
+        zjmp %:live
+";
+
+    #[test]
+    fn compiled_header_round_trip() {
+        let output = compile(ZORK).unwrap();
+        let champion = Champion::new(&mut output.as_slice()).unwrap();
+
+        assert_eq!(champion.name, "zork");
+        assert_eq!(champion.comment, "I'M ALIIIIVE");
+        assert_eq!(champion.program.as_slice(), &output[HEADER_SIZE..]);
+    }
+
+    #[test]
+    fn clamped_name_keeps_char_boundaries() {
+        let name = "é".repeat(PROG_NAME_LENGTH);
+        let input = ZORK.replace("zork", &name);
+        let output = compile(&input).unwrap();
+        let header = Header::decode(&output).unwrap();
+
+        assert_eq!(header.prog_name, "é".repeat(PROG_NAME_LENGTH / 2));
     }
 }
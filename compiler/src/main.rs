@@ -4,20 +4,20 @@ extern crate compiler;
 use std::{process, error};
 use std::env::args;
 use std::fs::File;
-use std::io::{self, copy, Read, Error, ErrorKind};
+use std::io::{self, copy, Read, Error};
 use std::path::Path;
 use compiler::compile;
 
-fn failable_main() -> Result<(), Box<error::Error>> {
+fn failable_main() -> Result<(), Box<dyn error::Error>> {
     let _ = env_logger::init();
 
     let mut args = args().skip(1);
     if args.size_hint().0 > 1 {
-        return Err(Box::new(Error::new(ErrorKind::Other, "Too many arguments.")))
+        return Err(Box::new(Error::other("Too many arguments.")))
     }
 
     let path = args.next().ok_or_else(|| {
-                    Box::new(Error::new(ErrorKind::Other, "Missing champion.s file to compile."))
+                    Box::new(Error::other("Missing champion.s file to compile."))
                 })?;
 
     let path = Path::new(&path);
@@ -29,7 +29,7 @@ fn failable_main() -> Result<(), Box<error::Error>> {
         buf
     };
 
-    compile(&input).map_err(|e| Box::new(io::Error::new(ErrorKind::Other, e.to_string())))
+    compile(&input).map_err(|e| Box::new(io::Error::other(e.to_string())))
         .and_then(|out| {
             let path = path.with_extension("cor").file_name().unwrap().to_string_lossy().to_string();
             File::create(&path)
@@ -37,7 +37,7 @@ fn failable_main() -> Result<(), Box<error::Error>> {
                 .map(|_| path)
                 .map_err(Box::new)
         }).map(|path| println!("Writing output program to {:?}", path))
-        .map_err(|e| e as Box<error::Error>)
+        .map_err(|e| e as Box<dyn error::Error>)
 }
 
 fn main() {
@@ -1,18 +1,14 @@
 mod var_dir_ind;
 mod var_dir_ind_reg;
-mod var_dir_reg;
 mod var_ind_reg;
 
-mod var_alt_dir_ind;
 mod var_alt_dir_ind_reg;
 mod var_alt_dir_reg;
 
 pub use self::var_dir_ind::VarDirInd;
 pub use self::var_dir_ind_reg::VarDirIndReg;
-pub use self::var_dir_reg::VarDirReg;
 pub use self::var_ind_reg::VarIndReg;
 
-pub use self::var_alt_dir_ind::VarAltDirInd;
 pub use self::var_alt_dir_ind_reg::VarAltDirIndReg;
 pub use self::var_alt_dir_reg::VarAltDirReg;
 
@@ -53,7 +49,7 @@ impl FromPair for Variable<Direct> {
                 let span_value = pair_value.clone().into_span();
                 match pair_value.as_rule() {
                     ::Rule::number => {
-                        let number = i32::from_str_radix(span_value.clone().as_str(), 10);
+                        let number = span_value.clone().as_str().parse::<i32>();
                         number.map(|n| Variable::Complete(Direct(n)))
                               .map_err(|e| Error::CustomErrorSpan { message: e.to_string(), span: span_value })
                     },
@@ -95,7 +91,7 @@ impl FromPair for Variable<AltDirect> {
                 let span_value = pair_value.clone().into_span();
                 match pair_value.as_rule() {
                     ::Rule::number => {
-                        let number = i16::from_str_radix(span_value.clone().as_str(), 10);
+                        let number = span_value.clone().as_str().parse::<i16>();
                         number.map(|n| Variable::Complete(AltDirect(n)))
                               .map_err(|e| Error::CustomErrorSpan { message: e.to_string(), span: span_value })
                     },
@@ -137,7 +133,7 @@ impl FromPair for Variable<Indirect> {
                 let span_value = pair_value.clone().into_span();
                 match pair_value.as_rule() {
                     ::Rule::number => {
-                        let number = i16::from_str_radix(span_value.clone().as_str(), 10);
+                        let number = span_value.clone().as_str().parse::<i16>();
                         number.map(|n| Variable::Complete(Indirect(n)))
                               .map_err(|e| Error::CustomErrorSpan { message: e.to_string(), span: span_value })
                     },
@@ -177,7 +173,7 @@ impl FromPair for Register {
             ::Rule::register => {
                 let pair_number = pair.into_inner().next().expect("number not found");
                 let span_number = pair_number.clone().into_span();
-                let number = u8::from_str_radix(span_number.clone().as_str(), 10);
+                let number = span_number.clone().as_str().parse::<u8>();
                 number.map_err(|e| e.to_string())
                       .and_then(|n| Register::new(n).map_err(|e| e.to_string()))
                       .map_err(|message| Error::CustomErrorSpan { message, span: span_number })
@@ -0,0 +1,267 @@
+use std::io::{self, Read, Write};
+use std::{error, fmt, str};
+use ::{COREWAR_EXEC_MAGIC, PROG_NAME_LENGTH, COMMENT_LENGTH};
+
+const MAGIC_OFFSET:     usize = 0;
+const PROG_NAME_OFFSET: usize = MAGIC_OFFSET + 4;
+const PROG_SIZE_OFFSET: usize = (PROG_NAME_OFFSET + PROG_NAME_LENGTH + 1).next_multiple_of(4);
+const COMMENT_OFFSET:   usize = PROG_SIZE_OFFSET + 4;
+
+/// The size of an encoded header, fields are aligned on 4 bytes
+/// like the C structure used by the reference implementation.
+pub const HEADER_SIZE: usize = (COMMENT_OFFSET + COMMENT_LENGTH + 1).next_multiple_of(4);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeaderField {
+    Name,
+    Comment,
+}
+
+impl fmt::Display for HeaderField {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            HeaderField::Name => f.write_str("name"),
+            HeaderField::Comment => f.write_str("comment"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum HeaderError {
+    Io(io::Error),
+    InvalidMagic(u32),
+    InvalidSize(usize),
+    TooLong { field: HeaderField, len: usize, max_len: usize },
+    InvalidUtf8(HeaderField),
+    MissingNul(HeaderField),
+    /// The field would be cut at its first NUL byte once decoded.
+    InteriorNul(HeaderField),
+}
+
+impl From<io::Error> for HeaderError {
+    fn from(error: io::Error) -> Self {
+        HeaderError::Io(error)
+    }
+}
+
+impl error::Error for HeaderError {}
+
+impl fmt::Display for HeaderError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            HeaderError::Io(ref e) => e.fmt(f),
+            HeaderError::InvalidMagic(magic) => {
+                write!(f, "invalid magic number {:#x}, expected {:#x}", magic, COREWAR_EXEC_MAGIC)
+            },
+            HeaderError::InvalidSize(size) => {
+                write!(f, "header is {} bytes long, expected {}", size, HEADER_SIZE)
+            },
+            HeaderError::TooLong { field, len, max_len } => {
+                write!(f, "{} is {} bytes long, maximum is {}", field, len, max_len)
+            },
+            HeaderError::InvalidUtf8(field) => write!(f, "{} is not valid UTF-8", field),
+            HeaderError::MissingNul(field) => write!(f, "{} is not NUL terminated", field),
+            HeaderError::InteriorNul(field) => write!(f, "{} contains a NUL byte", field),
+        }
+    }
+}
+
+/// The header of a `.cor` file, the magic number is implicit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Header {
+    pub prog_name: String,
+    pub prog_size: u32,
+    pub comment: String,
+}
+
+impl Header {
+    /// Encodes the header in exactly `HEADER_SIZE` bytes,
+    /// integers are big endian and padding bytes are zeroes.
+    pub fn encode(&self) -> Result<[u8; HEADER_SIZE], HeaderError> {
+        let mut bytes = [0u8; HEADER_SIZE];
+
+        bytes[MAGIC_OFFSET..][..4].copy_from_slice(&COREWAR_EXEC_MAGIC.to_be_bytes());
+        encode_str(&mut bytes[PROG_NAME_OFFSET..][..PROG_NAME_LENGTH + 1], &self.prog_name, HeaderField::Name)?;
+        bytes[PROG_SIZE_OFFSET..][..4].copy_from_slice(&self.prog_size.to_be_bytes());
+        encode_str(&mut bytes[COMMENT_OFFSET..][..COMMENT_LENGTH + 1], &self.comment, HeaderField::Comment)?;
+
+        Ok(bytes)
+    }
+
+    /// Decodes a header from the first `HEADER_SIZE` bytes of the given slice.
+    pub fn decode(bytes: &[u8]) -> Result<Header, HeaderError> {
+        if bytes.len() < HEADER_SIZE {
+            return Err(HeaderError::InvalidSize(bytes.len()))
+        }
+
+        let magic = decode_u32(&bytes[MAGIC_OFFSET..]);
+        if magic != COREWAR_EXEC_MAGIC {
+            return Err(HeaderError::InvalidMagic(magic))
+        }
+
+        let prog_name = decode_str(&bytes[PROG_NAME_OFFSET..][..PROG_NAME_LENGTH + 1], HeaderField::Name)?;
+        let prog_size = decode_u32(&bytes[PROG_SIZE_OFFSET..]);
+        let comment = decode_str(&bytes[COMMENT_OFFSET..][..COMMENT_LENGTH + 1], HeaderField::Comment)?;
+
+        Ok(Header {
+            prog_name: prog_name.to_string(),
+            prog_size,
+            comment: comment.to_string(),
+        })
+    }
+
+    pub fn read_from<R: Read>(reader: &mut R) -> Result<Header, HeaderError> {
+        let mut bytes = Vec::with_capacity(HEADER_SIZE);
+        reader.take(HEADER_SIZE as u64).read_to_end(&mut bytes)?;
+        Header::decode(&bytes)
+    }
+
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> Result<(), HeaderError> {
+        let bytes = self.encode()?;
+        writer.write_all(&bytes)?;
+        Ok(())
+    }
+}
+
+fn decode_u32(bytes: &[u8]) -> u32 {
+    let mut be_bytes = [0u8; 4];
+    be_bytes.copy_from_slice(&bytes[..4]);
+    u32::from_be_bytes(be_bytes)
+}
+
+fn encode_str(field_bytes: &mut [u8], value: &str, field: HeaderField) -> Result<(), HeaderError> {
+    let max_len = field_bytes.len() - 1;
+    if value.len() > max_len {
+        return Err(HeaderError::TooLong { field, len: value.len(), max_len })
+    }
+    if value.contains('\0') {
+        return Err(HeaderError::InteriorNul(field))
+    }
+    field_bytes[..value.len()].copy_from_slice(value.as_bytes());
+    Ok(())
+}
+
+fn decode_str(field_bytes: &[u8], field: HeaderField) -> Result<&str, HeaderError> {
+    let len = field_bytes.iter().position(|&b| b == 0).ok_or(HeaderError::MissingNul(field))?;
+    str::from_utf8(&field_bytes[..len]).map_err(|_| HeaderError::InvalidUtf8(field))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header() -> Header {
+        Header {
+            prog_name: "zork".to_string(),
+            prog_size: 23,
+            comment: "I'M ALIIIIVE".to_string(),
+        }
+    }
+
+    #[test]
+    fn size_matches_reference() {
+        assert_eq!(HEADER_SIZE, 2192);
+    }
+
+    #[test]
+    fn exact_layout() {
+        let bytes = header().encode().unwrap();
+
+        assert_eq!(&bytes[..4], &[0x00, 0xea, 0x83, 0xf3]);
+        assert_eq!(&bytes[4..9], b"zork\0");
+        assert_eq!(&bytes[136..140], &[0, 0, 0, 23]);
+        assert_eq!(&bytes[140..153], b"I'M ALIIIIVE\0");
+        assert!(bytes[153..].iter().all(|&b| b == 0));
+    }
+
+    #[test]
+    fn round_trip() {
+        let bytes = header().encode().unwrap();
+        assert_eq!(Header::decode(&bytes).unwrap(), header());
+    }
+
+    #[test]
+    fn round_trip_longest_fields() {
+        let header = Header {
+            prog_name: "n".repeat(PROG_NAME_LENGTH),
+            prog_size: u32::MAX,
+            comment: "c".repeat(COMMENT_LENGTH),
+        };
+
+        let mut output = Vec::new();
+        header.write_to(&mut output).unwrap();
+        assert_eq!(output.len(), HEADER_SIZE);
+        assert_eq!(Header::read_from(&mut output.as_slice()).unwrap(), header);
+    }
+
+    #[test]
+    fn name_too_long() {
+        let header = Header { prog_name: "n".repeat(PROG_NAME_LENGTH + 1), ..header() };
+        match header.encode() {
+            Err(HeaderError::TooLong { field: HeaderField::Name, len, max_len }) => {
+                assert_eq!(len, PROG_NAME_LENGTH + 1);
+                assert_eq!(max_len, PROG_NAME_LENGTH);
+            },
+            other => panic!("unexpected {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn interior_nul() {
+        let nul = Header { comment: "I'M\0ALIIIIVE".to_string(), ..header() };
+        match nul.encode() {
+            Err(HeaderError::InteriorNul(HeaderField::Comment)) => (),
+            other => panic!("unexpected {:?}", other.map(|_| ())),
+        }
+
+        // the accepted fields are decoded as they were
+        let control = Header { prog_name: "z\u{1}rk".to_string(), ..header() };
+        let mut output = Vec::new();
+        control.write_to(&mut output).unwrap();
+        assert_eq!(Header::read_from(&mut output.as_slice()).unwrap(), control);
+        let nul = Header { prog_name: "zo\0rk".to_string(), ..control };
+        match nul.write_to(&mut Vec::new()) {
+            Err(HeaderError::InteriorNul(HeaderField::Name)) => (),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn bad_magic() {
+        let mut bytes = header().encode().unwrap();
+        bytes[3] = 0xf4;
+        match Header::decode(&bytes) {
+            Err(HeaderError::InvalidMagic(0xea83f4)) => (),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn truncated() {
+        let bytes = header().encode().unwrap();
+        match Header::read_from(&mut &bytes[..100]) {
+            Err(HeaderError::InvalidSize(100)) => (),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn invalid_utf8() {
+        let mut bytes = header().encode().unwrap();
+        bytes[140] = 0xff;
+        match Header::decode(&bytes) {
+            Err(HeaderError::InvalidUtf8(HeaderField::Comment)) => (),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn missing_nul() {
+        let mut bytes = header().encode().unwrap();
+        for b in &mut bytes[4..4 + PROG_NAME_LENGTH + 1] { *b = b'n' }
+        match Header::decode(&bytes) {
+            Err(HeaderError::MissingNul(HeaderField::Name)) => (),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+}
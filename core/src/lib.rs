@@ -1,3 +1,8 @@
+mod header;
+
+use std::{error, fmt};
+pub use header::{Header, HeaderError, HeaderField, HEADER_SIZE};
+
 pub const MAX_ARGS_NUMBER: usize = 4;
 pub const MAX_PLAYERS:     usize = 4;
 pub const MEM_SIZE:        usize = 4 * 1024;
@@ -16,10 +21,117 @@ pub const PROG_NAME_LENGTH:     usize = 128;
 pub const COMMENT_LENGTH:       usize = 2048;
 pub const COREWAR_EXEC_MAGIC:     u32 = 0xea83f3;
 
-#[repr(C)]
-pub struct Header {
-    pub magic: u32,
-    pub prog_name: [u8; PROG_NAME_LENGTH + 1],
-    pub prog_size: u32,
-    pub comment: [u8; COMMENT_LENGTH + 1],
+/// How instructions are decoded and executed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Semantics {
+    /// The historical behaviour of this machine, instructions are decoded
+    /// entirely when a process starts waiting for them.
+    #[default]
+    Native,
+    /// Follows the official VM: only the opcode is read when a process starts waiting,
+    /// invalid param codes and registers skip the announced arguments
+    /// and `lld`/`lldi` set the carry from the loaded value.
+    Reference,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigError {
+    EmptyArena,
+    ZeroIdxMod,
+    InvalidRegNumber(usize),
+}
+
+impl error::Error for ConfigError {}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ConfigError::EmptyArena => f.write_str("the arena size must not be zero"),
+            ConfigError::ZeroIdxMod => f.write_str("idx_mod must not be zero"),
+            ConfigError::InvalidRegNumber(number) => {
+                write!(f, "{} registers requested, there must be between 1 and {}", number, REG_MAX)
+            },
+        }
+    }
+}
+
+/// The parameters of the virtual machine that can be changed at runtime,
+/// the default values are the constants above.
+///
+/// `reg_number` must be between 1 and `REG_MAX`, instructions are still
+/// decoded as if there were `REG_MAX` registers but the ones using
+/// a register above `reg_number` are considered invalid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VmConfig {
+    pub mem_size: usize,
+    pub idx_mod: usize,
+    pub champ_max_size: usize,
+    pub reg_number: usize,
+    pub cycle_to_die: usize,
+    pub cycle_delta: usize,
+    pub nbr_live: usize,
+    pub max_checks: usize,
+    pub semantics: Semantics,
+}
+
+impl VmConfig {
+    /// Returns the default config with an arena of `mem_size` bytes,
+    /// `idx_mod` and `champ_max_size` are scaled like the default ones,
+    /// `idx_mod` is at least 1.
+    pub fn with_mem_size(mem_size: usize) -> Self {
+        VmConfig {
+            mem_size,
+            idx_mod: (mem_size / 8).max(1),
+            champ_max_size: mem_size / 6,
+            ..VmConfig::default()
+        }
+    }
+
+    /// Checks the parameters the machine divides by or allocates from.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.mem_size == 0 {
+            return Err(ConfigError::EmptyArena)
+        }
+        if self.idx_mod == 0 {
+            return Err(ConfigError::ZeroIdxMod)
+        }
+        if self.reg_number == 0 || self.reg_number > REG_MAX as usize {
+            return Err(ConfigError::InvalidRegNumber(self.reg_number))
+        }
+        Ok(())
+    }
+}
+
+impl Default for VmConfig {
+    fn default() -> Self {
+        VmConfig {
+            mem_size: MEM_SIZE,
+            idx_mod: IDX_MOD,
+            champ_max_size: CHAMP_MAX_SIZE,
+            reg_number: REG_NUMBER,
+            cycle_to_die: CYCLE_TO_DIE,
+            cycle_delta: CYCLE_DELTA,
+            nbr_live: NBR_LIVE,
+            max_checks: MAX_CHECKS,
+            semantics: Semantics::Native,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validate() {
+        assert_eq!(VmConfig::default().validate(), Ok(()));
+        assert_eq!(VmConfig::with_mem_size(7).idx_mod, 1);
+        assert_eq!(VmConfig::with_mem_size(7).validate(), Ok(()));
+        assert_eq!(VmConfig::with_mem_size(0).validate(), Err(ConfigError::EmptyArena));
+        assert_eq!(VmConfig { idx_mod: 0, ..VmConfig::default() }.validate(), Err(ConfigError::ZeroIdxMod));
+        for &reg_number in &[0, REG_NUMBER + 1] {
+            let config = VmConfig { reg_number, ..VmConfig::default() };
+            assert_eq!(config.validate(), Err(ConfigError::InvalidRegNumber(reg_number)));
+        }
+    }
 }
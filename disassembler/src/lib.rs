@@ -0,0 +1,237 @@
+#[macro_use] extern crate log;
+pub extern crate core;
+extern crate machine;
+
+use std::collections::BTreeSet;
+use std::{error, fmt};
+use machine::champion::Champion;
+use machine::instruction::Instruction;
+use machine::instruction::Error as InstrError;
+use machine::instruction::mem_size::MemSize;
+use machine::instruction::parameter::{AltDirect, AltDirIndReg, Indirect};
+use core::HeaderField;
+
+#[derive(Debug)]
+pub enum Error {
+    /// The bytes at this offset of the program are not a valid instruction.
+    InvalidInstruction { offset: usize, error: InstrError },
+    /// The instruction at this offset would not be compiled back to the same bytes,
+    /// its parameter code has unused bits set for example.
+    NonCanonicalEncoding { offset: usize },
+    /// This property can't be written in a source file.
+    UnrepresentableProperty(HeaderField),
+}
+
+impl error::Error for Error {}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::InvalidInstruction { offset, ref error } => {
+                write!(f, "invalid instruction at offset {}: {:?}", offset, error)
+            },
+            Error::NonCanonicalEncoding { offset } => {
+                write!(f, "instruction at offset {} has a non canonical encoding", offset)
+            },
+            Error::UnrepresentableProperty(field) => {
+                write!(f, "champion {} can't be written in a source file", field)
+            },
+        }
+    }
+}
+
+/// A parameter of an instruction that is an offset to another part of the program.
+struct LabelRef {
+    param: usize,
+    direct: bool,
+    target: isize,
+}
+
+fn label_ref(instr: &Instruction, offset: usize) -> Option<LabelRef> {
+    fn index_param(param: AltDirIndReg) -> Option<(bool, i16)> {
+        match param {
+            AltDirIndReg::AltDirect(AltDirect(value)) => Some((true, value)),
+            AltDirIndReg::Indirect(Indirect(value)) => Some((false, value)),
+            AltDirIndReg::Register(_) => None,
+        }
+    }
+
+    let (param, value) = match *instr {
+        Instruction::ZJump(AltDirect(value)) => (0, Some((true, value))),
+        Instruction::Fork(AltDirect(value)) => (0, Some((true, value))),
+        Instruction::LongFork(AltDirect(value)) => (0, Some((true, value))),
+        Instruction::LoadIndex(a, _, _) => (0, index_param(a)),
+        Instruction::LongLoadIndex(a, _, _) => (0, index_param(a)),
+        Instruction::StoreIndex(_, b, _) => (1, index_param(b)),
+        _ => return None,
+    };
+
+    value.map(|(direct, value)| LabelRef { param, direct, target: offset as isize + value as isize })
+}
+
+fn label_name(offset: usize) -> String {
+    format!("label_{}", offset)
+}
+
+/// Decodes the whole program, making sure each instruction
+/// would be written back to the exact same bytes.
+fn decode(program: &[u8]) -> Result<Vec<(usize, Instruction)>, Error> {
+    let mut instructions = Vec::new();
+    let mut offset = 0;
+
+    while offset < program.len() {
+        let instr = Instruction::read_from(&program[offset..])
+                        .map_err(|error| Error::InvalidInstruction { offset, error })?;
+        let size = instr.mem_size();
+
+        let mut encoded = Vec::with_capacity(size);
+        instr.write_to(&mut encoded).expect("writing to a vec can't fail");
+        if encoded[..] != program[offset..offset + size] {
+            return Err(Error::NonCanonicalEncoding { offset })
+        }
+
+        trace!("decoded {} at offset {}", instr, offset);
+        instructions.push((offset, instr));
+        offset += size;
+    }
+
+    Ok(instructions)
+}
+
+fn format_instr(instr: &Instruction, offset: usize, labels: &BTreeSet<usize>) -> String {
+    let text = instr.to_string();
+    let label_ref = label_ref(instr, offset).filter(|l| l.target >= 0 && labels.contains(&(l.target as usize)));
+
+    match label_ref {
+        Some(LabelRef { param, direct, target }) => {
+            let (mnemonic, params) = text.split_at(text.find(' ').unwrap_or(text.len()));
+            let mut params: Vec<_> = params.trim_start().split(", ").map(ToString::to_string).collect();
+            let prefix = if direct { "%:" } else { ":" };
+            params[param] = format!("{}{}", prefix, label_name(target as usize));
+            format!("{} {}", mnemonic, params.join(", "))
+        },
+        None => text,
+    }
+}
+
+/// Returns the assembly source of the given champion, labels are synthesized
+/// for the jump, fork and index targets that land on an instruction.
+///
+/// The source compiles back to the exact same bytes.
+pub fn disassemble(champion: &Champion) -> Result<String, Error> {
+    if champion.name.is_empty() || champion.name.contains('"') {
+        return Err(Error::UnrepresentableProperty(HeaderField::Name))
+    }
+    if champion.comment.contains('"') {
+        return Err(Error::UnrepresentableProperty(HeaderField::Comment))
+    }
+
+    let program = champion.program.as_slice();
+    let instructions = decode(program)?;
+
+    let boundaries: BTreeSet<_> = instructions.iter().map(|&(offset, _)| offset)
+                                        .chain(Some(program.len())).collect();
+    let labels: BTreeSet<_> = instructions.iter()
+                                .filter_map(|&(offset, ref instr)| label_ref(instr, offset))
+                                .filter(|l| l.target >= 0 && boundaries.contains(&(l.target as usize)))
+                                .map(|l| l.target as usize)
+                                .collect();
+
+    let mut output = format!(".name \"{}\"\n.comment \"{}\"\n\n", champion.name, champion.comment);
+    for &(offset, ref instr) in &instructions {
+        if labels.contains(&offset) {
+            output.push_str(&format!("{}:\n", label_name(offset)));
+        }
+        output.push_str(&format!("\t{}\n", format_instr(instr, offset, &labels)));
+    }
+    if labels.contains(&program.len()) {
+        output.push_str(&format!("{}:\n", label_name(program.len())));
+    }
+
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate compiler;
+
+    use super::*;
+
+    const ZORK: &str = ".name \"zork\"
+.comment \"I'M ALIIIIVE\"
+
+l2:     sti r1, %:live, %1
+        and r1, %0, r1
+live:   live %1
+        zjmp %:live
+        ldi :l2, %3, r4
+        fork %:end
+        lfork %-20
+        ld -4, r2
+        lld %7, r3
+        st r2, 12
+        add r2, r3, r4
+        sub r2, r3, r4
+        or r1, 42, r5
+        xor %1, r5, r6
+        lldi r2, r3, r7
+        aff r6
+end:
+";
+
+    fn champion(source: &str) -> (Vec<u8>, Champion) {
+        let output = compiler::compile(source).unwrap();
+        let champion = Champion::new(&mut output.as_slice()).unwrap();
+        (output, champion)
+    }
+
+    #[test]
+    fn recompiles_to_identical_bytes() {
+        let (output, champion) = champion(ZORK);
+        let source = disassemble(&champion).unwrap();
+        assert_eq!(compiler::compile(&source).unwrap(), output);
+    }
+
+    #[test]
+    fn synthesizes_labels() {
+        let (_, champion) = champion(ZORK);
+        let source = disassemble(&champion).unwrap();
+
+        assert!(source.contains("\tsti r1, %:label_15, %1\n"));
+        assert!(source.contains("label_15:\n\tlive %1\n\tzjmp %:label_15\n"));
+        assert!(source.contains("\tldi :label_0, %3, r4\n"));
+        assert!(source.contains("\tlfork %-20\n"));
+        assert!(source.ends_with("\taff r6\nlabel_82:\n"));
+    }
+
+    #[test]
+    fn non_canonical_param_code() {
+        let (mut output, _) = champion(".name \"zork\"\nld %1, r2\n");
+        let param_code = output.len() - 6;
+        output[param_code] |= 0b0000_0011;
+
+        let champion = Champion::new(&mut output.as_slice()).unwrap();
+        match disassemble(&champion) {
+            Err(Error::NonCanonicalEncoding { offset: 0 }) => (),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn truncated_instruction() {
+        let header = core::Header {
+            prog_name: "zork".to_string(),
+            prog_size: 4,
+            comment: String::new(),
+        };
+        let mut output = Vec::new();
+        header.write_to(&mut output).unwrap();
+        output.extend_from_slice(&[1, 0, 0, 0]);
+
+        let champion = Champion::new(&mut output.as_slice()).unwrap();
+        match disassemble(&champion) {
+            Err(Error::InvalidInstruction { offset: 0, .. }) => (),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,32 @@
+extern crate env_logger;
+extern crate disassembler;
+extern crate machine;
+
+use std::{process, error};
+use std::env::args;
+use std::fs::File;
+use machine::champion::Champion;
+use disassembler::disassemble;
+
+fn failable_main() -> Result<(), Box<dyn error::Error>> {
+    let _ = env_logger::init();
+
+    let mut args = args().skip(1);
+    if args.size_hint().0 > 1 {
+        return Err("Too many arguments.".into())
+    }
+
+    let path = args.next().ok_or("Missing champion.cor file to disassemble.")?;
+    let mut file = File::open(&path)?;
+    let champion = Champion::new(&mut file)?;
+
+    print!("{}", disassemble(&champion)?);
+    Ok(())
+}
+
+fn main() {
+    if let Err(err) = failable_main() {
+        eprintln!("{}", err);
+        process::exit(1);
+    }
+}
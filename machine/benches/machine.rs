@@ -0,0 +1,164 @@
+//! Runs representative matches and reports how fast the machine executes them,
+//! `cargo bench -p machine --features bench-support --bench machine [workload...]`.
+
+extern crate machine;
+
+use std::collections::BTreeMap;
+use std::env;
+use std::io;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+use machine::{Limits, Machine};
+use machine::champion::Champion;
+use machine::instruction::Instruction;
+use machine::instruction::parameter::{AltDirect, AltDirIndReg, AltDirReg, Direct, DirInd, Indirect, IndReg, Register};
+use machine::observer::Event;
+use machine::test_support::named_champion as champion;
+use machine::core::VmConfig;
+
+struct Workload {
+    name: &'static str,
+    /// Builds the champion of each player from its number.
+    players: Vec<fn(i32) -> Champion>,
+    limits: Limits,
+    /// The number of times the match is played.
+    runs: usize,
+}
+
+fn reg(number: u8) -> Register {
+    Register::new(number).unwrap()
+}
+
+fn fork_bomb(id: i32) -> Champion {
+    // live, fork to the live and jump back to it, the processes double every 835 cycles
+    champion("fork bomb", &[
+        Instruction::Live(Direct(-id)),
+        Instruction::Fork(AltDirect(-5)),
+        Instruction::Load(DirInd::Direct(Direct(0)), reg(2)),
+        Instruction::ZJump(AltDirect(-15)),
+    ])
+}
+
+fn imp(id: i32) -> Champion {
+    // copies itself 64 bytes further four bytes at a time then jumps to its copy
+    champion("imp", &[
+        Instruction::Load(DirInd::Direct(Direct(0)), reg(3)),
+        Instruction::Load(DirInd::Direct(Direct(4)), reg(4)),
+        Instruction::Load(DirInd::Direct(Direct(64)), reg(5)),
+        Instruction::Live(Direct(-id)),
+        // r6 = [r3], [r3 + 64] = r6
+        Instruction::LoadIndex(AltDirIndReg::Register(reg(3)), AltDirReg::AltDirect(AltDirect(-26)), reg(6)),
+        Instruction::StoreIndex(reg(6), AltDirIndReg::Register(reg(3)), AltDirReg::AltDirect(AltDirect(64 - 32))),
+        Instruction::Addition(reg(3), reg(4), reg(3)),
+        Instruction::Substraction(reg(3), reg(5), reg(7)),
+        Instruction::ZJump(AltDirect(64 - 48)),
+        Instruction::Load(DirInd::Direct(Direct(0)), reg(8)),
+        Instruction::ZJump(AltDirect(21 - 58)),
+    ])
+}
+
+fn bomber(id: i32) -> Champion {
+    // eight processes writing its id every four bytes, away from itself
+    champion("bomber", &[
+        Instruction::Load(DirInd::Direct(Direct(-4)), reg(4)),
+        Instruction::Fork(AltDirect(3)),
+        Instruction::Fork(AltDirect(3)),
+        Instruction::Fork(AltDirect(3)),
+        Instruction::Live(Direct(-id)),
+        Instruction::StoreIndex(reg(1), AltDirIndReg::Register(reg(3)), AltDirReg::AltDirect(AltDirect(-20))),
+        Instruction::Addition(reg(3), reg(4), reg(3)),
+        Instruction::Load(DirInd::Direct(Direct(0)), reg(5)),
+        Instruction::ZJump(AltDirect(-23)),
+    ])
+}
+
+fn forker(id: i32) -> Champion {
+    // lives in a loop while a long fork bombs around it
+    champion("forker", &[
+        Instruction::StoreIndex(reg(1), AltDirIndReg::AltDirect(AltDirect(13)), AltDirReg::AltDirect(AltDirect(1))),
+        Instruction::Fork(AltDirect(6)),
+        Instruction::LongFork(AltDirect(11)),
+        Instruction::Live(Direct(-id)),
+        Instruction::ZJump(AltDirect(-5)),
+        Instruction::Store(reg(1), IndReg::Indirect(Indirect(50))),
+        Instruction::Store(reg(1), IndReg::Indirect(Indirect(-70))),
+        Instruction::Load(DirInd::Direct(Direct(0)), reg(2)),
+        Instruction::ZJump(AltDirect(-17)),
+    ])
+}
+
+fn workloads() -> Vec<Workload> {
+    vec![
+        Workload {
+            name: "fork-bomb",
+            players: vec![fork_bomb, fork_bomb],
+            limits: Limits { max_cycles: Some(16_000), max_processes: Some(250_000), ..Limits::default() },
+            runs: 1,
+        },
+        Workload {
+            name: "imp",
+            players: vec![imp],
+            limits: Limits { max_cycles: Some(100_000), ..Limits::default() },
+            runs: 50,
+        },
+        Workload {
+            name: "sti-bomber",
+            players: vec![bomber, bomber],
+            limits: Limits { max_cycles: Some(50_000), ..Limits::default() },
+            runs: 50,
+        },
+        Workload {
+            name: "4-players",
+            players: vec![fork_bomb, imp, bomber, forker],
+            limits: Limits { max_cycles: Some(100_000), max_processes: Some(100_000), ..Limits::default() },
+            runs: 1,
+        },
+    ]
+}
+
+/// Plays a match and returns the machine once it is over,
+/// with the largest number of processes alive at the end of a cycle.
+fn play(workload: &Workload, instructions: &Arc<AtomicUsize>) -> (Machine, usize) {
+    let champions: BTreeMap<i32, _> = (1..).zip(&workload.players).map(|(id, player)| (id, player(id))).collect();
+    let mut machine = Machine::new(champions, VmConfig::default()).unwrap();
+    machine.set_limits(workload.limits);
+    let counter = instructions.clone();
+    machine.add_observer(Box::new(move |event: &Event| {
+        if let Event::InstructionExecuted { .. } = *event {
+            counter.fetch_add(1, Ordering::Relaxed);
+        }
+    }));
+    let mut peak = 0;
+    machine.run(&mut io::sink(), |info| peak = peak.max(info.remaining_processes)).unwrap();
+    (machine, peak)
+}
+
+fn run(workload: &Workload) {
+    let instructions = Arc::new(AtomicUsize::new(0));
+    let (mut cycles, mut processes, mut rejected_forks) = (0, 0, 0);
+    let start = Instant::now();
+    for _ in 0..workload.runs {
+        let (machine, peak) = play(workload, &instructions);
+        cycles += machine.cycle();
+        processes = processes.max(peak);
+        rejected_forks += machine.rejected_forks();
+    }
+    let secs = start.elapsed().as_secs_f64();
+
+    let instructions = instructions.load(Ordering::Relaxed);
+    println!("{} ({} runs): {} cycles, {} instructions, {} processes at most, {} forks rejected",
+             workload.name, workload.runs, cycles, instructions, processes, rejected_forks);
+    println!("    {:.3}s, {:.0} cycles/s, {:.0} instructions/s",
+             secs, cycles as f64 / secs, instructions as f64 / secs);
+}
+
+fn main() {
+    // cargo passes `--bench` to the benchmarks
+    let filters: Vec<_> = env::args().skip(1).filter(|arg| !arg.starts_with('-')).collect();
+    for workload in workloads() {
+        if filters.is_empty() || filters.iter().any(|filter| workload.name.contains(filter.as_str())) {
+            run(&workload);
+        }
+    }
+}
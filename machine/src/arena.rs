@@ -1,33 +1,174 @@
 use std::io::{self, Read, Write};
-use std::fmt;
-use core::MEM_SIZE;
+use std::{fmt, mem};
+use snapshot::{SnapshotError, write_option_i32, read_option_i32};
+use decode::DecodeCache;
+use instruction::Instruction;
 
 pub struct Arena {
-    memory: [u8; MEM_SIZE],
+    memory: Vec<u8>,
+    owners: Vec<Option<i32>>,
+    writes: Option<Vec<ArenaWrite>>,
+    decoded: DecodeCache,
+}
+
+/// The content of an arena byte before it was overwritten.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ArenaWrite {
+    pub index: usize,
+    pub old_value: u8,
+    pub old_owner: Option<i32>,
 }
 
 impl Arena {
-    pub fn new() -> Self {
-        Arena { memory: [0; MEM_SIZE] }
+    pub fn new(mem_size: usize) -> Self {
+        Arena {
+            memory: vec![0; mem_size],
+            owners: vec![None; mem_size],
+            writes: None,
+            decoded: DecodeCache::new(mem_size),
+        }
     }
 
-    pub fn read_from(&self, ArenaIndex(index): ArenaIndex) -> ArenaReader {
+    pub fn size(&self) -> usize {
+        self.memory.len()
+    }
+
+    pub fn index(&self, index: usize) -> ArenaIndex {
+        ArenaIndex::from_raw(index, self.size())
+    }
+
+    pub fn read_from(&self, index: ArenaIndex) -> ArenaReader<'_> {
         ArenaReader { index, arena: self }
     }
 
-    pub fn write_to(&mut self, ArenaIndex(index): ArenaIndex) -> ArenaWriter {
-        ArenaWriter { index, arena: self }
+    /// Every byte written through the returned writer will be owned by `owner`.
+    pub fn write_to(&mut self, index: ArenaIndex, owner: i32) -> ArenaWriter<'_> {
+        ArenaWriter { index, owner, arena: self }
+    }
+
+    /// Fills `buf` with the bytes from `index`, wrapping around the end of the arena.
+    pub fn read_bytes(&self, ArenaIndex { mut index, .. }: ArenaIndex, buf: &mut [u8]) {
+        for byte in buf {
+            *byte = self.memory[index];
+            index = (index + 1) % self.memory.len();
+        }
+    }
+
+    /// Writes `buf` from `index`, wrapping around the end of the arena,
+    /// every byte written will be owned by `owner`.
+    pub fn write_bytes(&mut self, ArenaIndex { mut index, .. }: ArenaIndex, owner: i32, buf: &[u8]) {
+        for &byte in buf {
+            if let Some(ref mut writes) = self.writes {
+                writes.push(ArenaWrite {
+                    index,
+                    old_value: self.memory[index],
+                    old_owner: self.owners[index],
+                });
+            }
+            self.memory[index] = byte;
+            self.owners[index] = Some(owner);
+            self.decoded.invalidate(index);
+            index = (index + 1) % self.memory.len();
+        }
+    }
+
+    /// Decodes the instruction at `index`, the decoding is reused
+    /// until one of the bytes it was read from is written.
+    pub fn decode(&mut self, index: ArenaIndex) -> Option<Instruction> {
+        match self.decoded.get(index.index) {
+            Some(instruction) => instruction,
+            None => {
+                // longer than any instruction
+                let mut bytes = [0; 16];
+                self.read_bytes(index, &mut bytes);
+                self.decoded.decode(index.index, &bytes[..])
+            },
+        }
+    }
+
+    pub fn byte(&self, ArenaIndex { index, .. }: ArenaIndex) -> u8 {
+        self.memory[index]
     }
 
     pub fn as_slice(&self) -> &[u8] {
         &self.memory
     }
+
+    /// Returns the id of the champion that last wrote the byte at this index.
+    pub fn owner(&self, ArenaIndex { index, .. }: ArenaIndex) -> Option<i32> {
+        self.owners[index]
+    }
+
+    /// Returns the owner of every byte, in the same order as `as_slice`.
+    pub fn owners(&self) -> &[Option<i32>] {
+        &self.owners
+    }
+
+    pub fn dump(&self, bytes_per_line: usize) -> Dump<'_> {
+        Dump { arena: self, bytes_per_line }
+    }
+
+    /// Starts or stops recording the previous content of every written byte.
+    pub fn record_writes(&mut self, enabled: bool) {
+        self.writes = if enabled { Some(self.writes.take().unwrap_or_default()) } else { None };
+    }
+
+    /// Returns the writes recorded since the last call.
+    pub fn take_writes(&mut self) -> Vec<ArenaWrite> {
+        self.writes.as_mut().map(mem::take).unwrap_or_default()
+    }
+
+    /// Restores the bytes overwritten by these writes, most recent last.
+    pub fn undo_writes(&mut self, writes: &[ArenaWrite]) {
+        for write in writes.iter().rev() {
+            self.memory[write.index] = write.old_value;
+            self.owners[write.index] = write.old_owner;
+            self.decoded.invalidate(write.index);
+        }
+    }
+
+    pub fn write_snapshot<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&self.memory)?;
+        for owner in &self.owners {
+            write_option_i32(writer, *owner)?;
+        }
+        Ok(())
+    }
+
+    /// Only allocates what the reader contains, `mem_size` may come from a corrupt snapshot.
+    pub fn read_snapshot<R: Read>(reader: &mut R, mem_size: usize) -> Result<Self, SnapshotError> {
+        let mut memory = Vec::new();
+        reader.take(mem_size as u64).read_to_end(&mut memory)?;
+        if memory.len() != mem_size {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into())
+        }
+        let owners = (0..mem_size).map(|_| read_option_i32(reader)).collect::<Result<_, _>>()?;
+        Ok(Arena { memory, owners, writes: None, decoded: DecodeCache::new(mem_size) })
+    }
 }
 
 impl fmt::Debug for Arena {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         for line in self.memory.chunks(32) {
-            for x in line { write!(f, "{:0<2x} ", x)? }
+            for x in line { write!(f, "{:02x} ", x)? }
+            writeln!(f)?
+        }
+        Ok(())
+    }
+}
+
+/// Displays the arena like the reference VM dumps it,
+/// `bytes_per_line` bytes per line prefixed by the address of the first one.
+pub struct Dump<'a> {
+    arena: &'a Arena,
+    bytes_per_line: usize,
+}
+
+impl<'a> fmt::Display for Dump<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, line) in self.arena.memory.chunks(self.bytes_per_line.max(1)).enumerate() {
+            write!(f, "{:#06x} : ", i * self.bytes_per_line)?;
+            for x in line { write!(f, "{:02x} ", x)? }
             writeln!(f)?
         }
         Ok(())
@@ -35,24 +176,32 @@ impl fmt::Debug for Arena {
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
-pub struct ArenaIndex(usize);
+pub struct ArenaIndex {
+    index: usize,
+    mem_size: usize,
+}
 
 impl ArenaIndex {
-    pub fn from_raw(index: usize) -> Self {
-        ArenaIndex::zero().advance_by(index)
+    pub fn from_raw(index: usize, mem_size: usize) -> Self {
+        ArenaIndex::zero(mem_size).advance_by(index)
     }
 
-    pub fn zero() -> Self {
-        ArenaIndex(0)
+    pub fn zero(mem_size: usize) -> Self {
+        ArenaIndex { index: 0, mem_size }
+    }
+
+    pub fn as_raw(self) -> usize {
+        self.index
     }
 
     pub fn advance_by(self, value: usize) -> Self {
-        ArenaIndex((self.0 + value) % MEM_SIZE)
+        ArenaIndex { index: (self.index + value) % self.mem_size, ..self }
     }
 
     pub fn move_by(self, value: isize) -> Self {
+        let mem_size = self.mem_size as isize;
         let value = if value < 0 {
-            ((value % MEM_SIZE as isize) + MEM_SIZE as isize) as usize
+            ((value % mem_size) + mem_size) as usize
         } else {
             value as usize
         };
@@ -61,43 +210,30 @@ impl ArenaIndex {
 }
 
 pub struct ArenaReader<'a> {
-    index: usize,
+    index: ArenaIndex,
     arena: &'a Arena,
 }
 
-// TODO: use memcpy
+/// Never fails nor reaches an end, reading wraps around the arena.
 impl<'a> Read for ArenaReader<'a> {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        let mut buf_index = 0;
-        while buf_index != buf.len() {
-            buf[buf_index] = self.arena.memory[self.index];
-            buf_index += 1;
-            self.index += 1;
-            if self.index == self.arena.memory.len() {
-                self.index = 0;
-            }
-        }
+        self.arena.read_bytes(self.index, buf);
+        self.index = self.index.advance_by(buf.len());
         Ok(buf.len())
     }
 }
 
 pub struct ArenaWriter<'a> {
-    index: usize,
+    index: ArenaIndex,
+    owner: i32,
     arena: &'a mut Arena,
 }
 
-// TODO: use memcpy
+/// Never fails, writing wraps around the arena.
 impl<'a> Write for ArenaWriter<'a> {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        let mut buf_index = 0;
-        while buf_index != buf.len() {
-            self.arena.memory[self.index] = buf[buf_index];
-            buf_index += 1;
-            self.index += 1;
-            if self.index == self.arena.memory.len() {
-                self.index = 0;
-            }
-        }
+        self.arena.write_bytes(self.index, self.owner, buf);
+        self.index = self.index.advance_by(buf.len());
         Ok(buf.len())
     }
 
@@ -111,13 +247,30 @@ mod tests {
     use super::*;
     use core::MEM_SIZE;
 
+    #[test]
+    fn debug_and_dump() {
+        let mut arena = Arena::new(64);
+        let index = arena.index(33);
+        arena.write_to(index, 1).write_all(&[0x0b, 0xff]).unwrap();
+
+        let debug = format!("{:?}", arena);
+        assert_eq!(debug.lines().nth(1).unwrap().get(..9), Some("00 0b ff "));
+
+        let dump = arena.dump(32).to_string();
+        let lines: Vec<_> = dump.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0], format!("0x0000 : {}", "00 ".repeat(32)));
+        assert!(lines[1].starts_with("0x0020 : 00 0b ff 00 "));
+        assert_eq!(arena.dump(64).to_string().lines().count(), 1);
+    }
+
     #[test]
     fn write_read_at_zero() {
-        let mut arena = Arena::new();
-        let index = ArenaIndex::from_raw(0);
+        let mut arena = Arena::new(MEM_SIZE);
+        let index = arena.index(0);
 
         {
-            let mut writer = arena.write_to(index);
+            let mut writer = arena.write_to(index, 1);
             let written = writer.write(&[42, 43, 44]).unwrap();
             assert_eq!(written, 3);
         }
@@ -133,11 +286,11 @@ mod tests {
 
     #[test]
     fn write_read_at_limit() {
-        let mut arena = Arena::new();
-        let index = ArenaIndex::from_raw(MEM_SIZE - 2);
+        let mut arena = Arena::new(MEM_SIZE);
+        let index = arena.index(MEM_SIZE - 2);
 
         {
-            let mut writer = arena.write_to(index);
+            let mut writer = arena.write_to(index, 1);
             let written = writer.write(&[42, 43, 44]).unwrap();
             assert_eq!(written, 3);
         }
@@ -150,4 +303,60 @@ mod tests {
             assert_eq!(&[42, 43, 44], &buf);
         }
     }
+
+    #[test]
+    fn write_records_owner() {
+        let mut arena = Arena::new(8);
+
+        arena.write_to(arena.index(6), 1).write_all(&[42, 43, 44]).unwrap();
+        arena.write_to(arena.index(7), -2).write_all(&[45]).unwrap();
+
+        assert_eq!(arena.owner(arena.index(6)), Some(1));
+        assert_eq!(arena.owner(arena.index(7)), Some(-2));
+        assert_eq!(arena.owner(arena.index(0)), Some(1));
+        assert_eq!(arena.owners(), &[Some(1), None, None, None, None, None, Some(1), Some(-2)]);
+    }
+
+    #[test]
+    fn index_wraps_around_small_arena() {
+        let arena = Arena::new(64);
+        let index = arena.index(60);
+
+        assert_eq!(index.advance_by(6).as_raw(), 2);
+        assert_eq!(index.move_by(-62).as_raw(), 62);
+        assert_eq!(arena.index(130).as_raw(), 2);
+    }
+
+    #[test]
+    fn decoding_follows_writes() {
+        let mut arena = Arena::new(32);
+        let uncached = |arena: &Arena, index| Instruction::read_from(arena.read_from(index)).ok();
+        // a xorshift to write pseudo random instructions and bytes all over the arena
+        let mut state = 0x2545_f491u32;
+        let mut random = move || {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state
+        };
+        for _ in 0..2000 {
+            let index = arena.index(random() as usize);
+            let mut bytes = vec![(random() % 17) as u8, [0x54, 0x64, 0x68, 0x94][random() as usize % 4]];
+            bytes.extend((0..random() % 6).map(|_| random() as u8));
+            arena.write_to(index, 1).write_all(&bytes).unwrap();
+            for i in 0..arena.size() {
+                let index = arena.index(i);
+                assert_eq!(arena.decode(index), uncached(&arena, index), "at {}", i);
+            }
+        }
+
+        arena.record_writes(true);
+        arena.write_to(arena.index(30), 1).write_all(&[1, 0, 0, 0, 1]).unwrap();
+        assert_eq!(arena.decode(arena.index(30)), uncached(&arena, arena.index(30)));
+        let writes = arena.take_writes();
+        arena.undo_writes(&writes);
+        for i in 0..arena.size() {
+            assert_eq!(arena.decode(arena.index(i)), uncached(&arena, arena.index(i)), "at {}", i);
+        }
+    }
 }
@@ -0,0 +1,257 @@
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Mutex;
+use std::sync::mpsc;
+use std::{error, fmt, thread};
+use champion::Champion;
+use machine::Machine;
+use placement::{PlacementError, PlacementStrategy};
+use limits::Limits;
+use outcome::{MatchOutcome, OutcomeRecorder};
+use core::VmConfig;
+
+/// A match to play in a batch.
+pub struct MatchSpec {
+    pub champions: BTreeMap<i32, Champion>,
+    pub strategy: PlacementStrategy,
+    pub config: VmConfig,
+    pub limits: Limits,
+    /// The number of processes is sampled in the outcome every `sample_interval` cycles.
+    pub sample_interval: usize,
+    /// Where the bytes displayed by `aff` are written, they are discarded if there is none.
+    pub talk: Option<Box<dyn Write + Send>>,
+}
+
+impl MatchSpec {
+    pub fn new(champions: BTreeMap<i32, Champion>, strategy: PlacementStrategy) -> Self {
+        MatchSpec {
+            champions,
+            strategy,
+            config: VmConfig::default(),
+            limits: Limits::default(),
+            sample_interval: 0,
+            talk: None,
+        }
+    }
+
+    /// Plays the match until it is over or a limit is reached.
+    pub fn play(self) -> Result<MatchOutcome, BatchError> {
+        let mut machine = Machine::with_strategy(self.champions, self.strategy, self.config)?;
+        machine.set_limits(self.limits);
+        let mut recorder = OutcomeRecorder::new(&machine, self.sample_interval);
+        let mut talk = self.talk.unwrap_or_else(|| Box::new(io::sink()));
+        let termination = machine.run(&mut talk, |cycle_info| recorder.record(cycle_info))?;
+        Ok(recorder.finish(&machine, termination))
+    }
+}
+
+#[derive(Debug)]
+pub enum BatchError {
+    Placement(PlacementError),
+    Io(io::Error),
+    /// Playing the match panicked.
+    Panicked,
+}
+
+impl From<PlacementError> for BatchError {
+    fn from(error: PlacementError) -> BatchError {
+        BatchError::Placement(error)
+    }
+}
+
+impl From<io::Error> for BatchError {
+    fn from(error: io::Error) -> BatchError {
+        BatchError::Io(error)
+    }
+}
+
+impl error::Error for BatchError {}
+
+impl fmt::Display for BatchError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            BatchError::Placement(ref error) => error.fmt(f),
+            BatchError::Io(ref error) => write!(f, "io error: {}", error),
+            BatchError::Panicked => write!(f, "the match panicked"),
+        }
+    }
+}
+
+/// Plays the matches on `threads` threads, at least one,
+/// the outcomes are in the same order as the matches.
+/// A match that panics fails alone, the others are still played.
+pub fn run_batch(matches: Vec<MatchSpec>, threads: usize) -> Vec<Result<MatchOutcome, BatchError>> {
+    let count = matches.len();
+    let jobs = Mutex::new(matches.into_iter().enumerate());
+    let (sender, receiver) = mpsc::channel();
+
+    thread::scope(|scope| {
+        for _ in 0..threads.clamp(1, count.max(1)) {
+            let sender = sender.clone();
+            let jobs = &jobs;
+            scope.spawn(move || loop {
+                // the lock is released before the match is played
+                let job = jobs.lock().ok().and_then(|mut jobs| jobs.next());
+                let (index, spec) = match job {
+                    Some(job) => job,
+                    None => break,
+                };
+                // the match is dropped once played, nothing it touched is used after a panic
+                let outcome = panic::catch_unwind(AssertUnwindSafe(|| spec.play()))
+                    .unwrap_or(Err(BatchError::Panicked));
+                if sender.send((index, outcome)).is_err() {
+                    break
+                }
+            });
+        }
+    });
+    drop(sender);
+
+    let mut outcomes: Vec<_> = (0..count).map(|_| Err(BatchError::Panicked)).collect();
+    for (index, outcome) in receiver {
+        outcomes[index] = outcome;
+    }
+    outcomes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use test_support::champion;
+    use instruction::Instruction;
+    use instruction::parameter::{AltDirect, Direct, DirInd, Register};
+    use outcome::{MatchResult, Termination};
+
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Panics on the first write.
+    struct PanickingOutput;
+
+    impl Write for PanickingOutput {
+        fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+            panic!("the output panicked")
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn assert_send<T: Send>() {}
+
+    #[test]
+    fn machines_and_champions_are_send() {
+        assert_send::<Machine>();
+        assert_send::<Champion>();
+        assert_send::<MatchSpec>();
+    }
+
+    #[test]
+    fn outcomes_keep_the_order_of_the_matches() {
+        let load = Instruction::Load(DirInd::Direct(Direct(0)), Register::new(2).unwrap());
+        let liver = champion(&[Instruction::Live(Direct(-1)), load, Instruction::ZJump(AltDirect(-12))]);
+        let quiet = champion(&[load, Instruction::ZJump(AltDirect(0))]);
+        let config = VmConfig { cycle_to_die: 100, ..VmConfig::with_mem_size(256) };
+
+        let matches = || (0..12).map(|seed| {
+            let mut champions = BTreeMap::new();
+            champions.insert(1, liver.clone());
+            champions.insert(2, quiet.clone());
+            let strategy = PlacementStrategy::Random { seed };
+            let mut spec = MatchSpec { config, ..MatchSpec::new(champions, strategy) };
+            // every other match is stopped before it is over
+            if seed % 2 == 1 {
+                spec.limits.max_cycles = Some(50);
+            }
+            spec
+        }).collect::<Vec<_>>();
+
+        let sequential = run_batch(matches(), 1);
+        let parallel = run_batch(matches(), 4);
+        assert_eq!(parallel.len(), 12);
+        for (seed, (sequential, parallel)) in sequential.into_iter().zip(parallel).enumerate() {
+            let (sequential, parallel) = (sequential.unwrap(), parallel.unwrap());
+            assert_eq!(parallel, sequential);
+            assert_eq!(parallel.placement_seed, Some(seed as u64));
+            if seed % 2 == 1 {
+                assert_eq!(parallel.termination, Termination::CycleLimit);
+                assert_eq!(parallel.result, MatchResult::Draw(vec![1, 2]));
+            } else {
+                assert_eq!(parallel.termination, Termination::AllProcessesDead);
+                assert_eq!(parallel.result, MatchResult::Winner(1));
+            }
+        }
+        assert!(run_batch(Vec::new(), 4).is_empty());
+    }
+
+    #[test]
+    fn errors_and_talk() {
+        let reg = Register::new(1).unwrap();
+        let mut champions = BTreeMap::new();
+        champions.insert(1, champion(&[Instruction::Display(reg)]));
+        let config = VmConfig::with_mem_size(256);
+        let talk = SharedBuffer::default();
+        let spoken = MatchSpec {
+            config,
+            talk: Some(Box::new(talk.clone())),
+            ..MatchSpec::new(champions.clone(), PlacementStrategy::Even)
+        };
+        let silent = MatchSpec { config, ..MatchSpec::new(champions, PlacementStrategy::Even) };
+        let load = Instruction::Load(DirInd::Direct(Direct(0)), Register::new(2).unwrap());
+        let mut too_large = BTreeMap::new();
+        too_large.insert(1, champion(&[load; 40]));
+        let too_large = MatchSpec { config, ..MatchSpec::new(too_large, PlacementStrategy::Even) };
+
+        let outcomes = run_batch(vec![spoken, too_large, silent], 2);
+        assert!(outcomes[0].is_ok());
+        assert!(outcomes[2].is_ok());
+        match outcomes[1] {
+            Err(BatchError::Placement(PlacementError::TooLarge { .. })) => (),
+            ref other => panic!("unexpected outcome {:?}", other),
+        }
+        // the process displays its id each time it wraps around the arena
+        let talk = talk.0.lock().unwrap();
+        assert!(!talk.is_empty() && talk.iter().all(|&byte| byte == 0xff));
+    }
+
+    #[test]
+    fn a_panicking_match_fails_alone() {
+        let reg = Register::new(1).unwrap();
+        let load = Instruction::Load(DirInd::Direct(Direct(0)), Register::new(2).unwrap());
+        let config = VmConfig::with_mem_size(256);
+        let matches = |talk: fn(usize) -> Option<Box<dyn Write + Send>>| (0..4).map(|index| {
+            let mut champions = BTreeMap::new();
+            champions.insert(1, champion(&[Instruction::Display(reg), load, Instruction::ZJump(AltDirect(-10))]));
+            MatchSpec { config, talk: talk(index), ..MatchSpec::new(champions, PlacementStrategy::Even) }
+        }).collect::<Vec<_>>();
+        let panicking = |index| match index {
+            2 => Some(Box::new(PanickingOutput) as Box<dyn Write + Send>),
+            _ => None,
+        };
+
+        for &threads in &[1, 3] {
+            let outcomes = run_batch(matches(panicking), threads);
+            assert_eq!(outcomes.len(), 4);
+            for (index, outcome) in outcomes.into_iter().enumerate() {
+                match (index, outcome) {
+                    (2, Err(BatchError::Panicked)) => (),
+                    (2, other) => panic!("unexpected outcome {:?}", other),
+                    (_, outcome) => assert_eq!(outcome.unwrap().termination, Termination::AllProcessesDead),
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,225 @@
+extern crate env_logger;
+extern crate machine;
+
+use std::collections::{BTreeMap, HashMap};
+use std::env::args;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::time::Duration;
+use std::{process, error, thread};
+use machine::{run_batch, Limits, MatchSpec, PlacementStrategy};
+use machine::champion::{Champion, LoadOptions};
+use machine::core::{Semantics, VmConfig};
+
+const USAGE: &str = "usage: corewar-batch [-j threads] [--placement even|random|random-order] [--reference] \
+                     [--max-cycles cycles] [--max-processes count] [--time-limit seconds] \
+                     [--sample-interval cycles] [batch-file]\n\
+                     every line of the batch file, or of the standard input, \
+                     is a placement seed followed by the champions of a match";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Placement {
+    Even,
+    Random,
+    RandomOrder,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct Options {
+    threads: Option<usize>,
+    placement: Placement,
+    semantics: Semantics,
+    limits: Limits,
+    sample_interval: usize,
+    /// Reads the matches from the standard input if there is none.
+    batch_file: Option<String>,
+}
+
+/// A line of the batch file.
+#[derive(Debug, PartialEq, Eq)]
+struct MatchLine {
+    seed: u64,
+    champions: Vec<String>,
+}
+
+fn parse_args<I: Iterator<Item = String>>(mut args: I) -> Result<Options, String> {
+    let mut threads = None;
+    let mut placement = Placement::Random;
+    let mut semantics = Semantics::Native;
+    let mut limits = Limits::default();
+    let mut sample_interval = 0;
+    let mut batch_file = None;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-j" => {
+                let value = args.next().ok_or("-j expects a number of threads")?;
+                threads = Some(value.parse().map_err(|_| format!("invalid number of threads {:?}", value))?);
+            },
+            "--placement" => {
+                placement = match args.next().as_deref() {
+                    Some("even") => Placement::Even,
+                    Some("random") => Placement::Random,
+                    Some("random-order") => Placement::RandomOrder,
+                    _ => return Err("--placement expects even, random or random-order".to_string()),
+                };
+            },
+            "--reference" => semantics = Semantics::Reference,
+            "--max-cycles" => {
+                let value = args.next().ok_or("--max-cycles expects a number of cycles")?;
+                limits.max_cycles = Some(value.parse().map_err(|_| format!("invalid cycle limit {:?}", value))?);
+            },
+            "--max-processes" => {
+                let value = args.next().ok_or("--max-processes expects a number of processes")?;
+                limits.max_processes = Some(value.parse().map_err(|_| format!("invalid process limit {:?}", value))?);
+            },
+            "--time-limit" => {
+                let value = args.next().ok_or("--time-limit expects a number of seconds")?;
+                let seconds = value.parse().ok().and_then(|seconds| Duration::try_from_secs_f64(seconds).ok());
+                limits.max_duration = Some(seconds.ok_or_else(|| format!("invalid time limit {:?}", value))?);
+            },
+            "--sample-interval" => {
+                let value = args.next().ok_or("--sample-interval expects a number of cycles")?;
+                sample_interval = value.parse().map_err(|_| format!("invalid sample interval {:?}", value))?;
+            },
+            flag if flag.starts_with('-') && flag != "-" => {
+                return Err(format!("unknown flag {}\n{}", flag, USAGE))
+            },
+            _ if batch_file.is_some() => return Err(format!("only one batch file is expected\n{}", USAGE)),
+            _ => batch_file = Some(arg).filter(|path| path != "-"),
+        }
+    }
+    Ok(Options { threads, placement, semantics, limits, sample_interval, batch_file })
+}
+
+/// Reads the matches, empty lines and lines starting with `#` are ignored.
+fn parse_matches<R: BufRead>(reader: R) -> Result<Vec<MatchLine>, String> {
+    let mut matches = Vec::new();
+    for (number, line) in reader.lines().enumerate() {
+        let line = line.map_err(|e| e.to_string())?;
+        let mut words = line.split_whitespace();
+        let seed = match words.next() {
+            Some(word) if !word.starts_with('#') => word,
+            _ => continue,
+        };
+        let seed = seed.parse().map_err(|_| format!("line {}: invalid seed {:?}", number + 1, seed))?;
+        let champions: Vec<_> = words.map(ToString::to_string).collect();
+        if champions.is_empty() {
+            return Err(format!("line {}: a match needs at least one champion", number + 1))
+        }
+        matches.push(MatchLine { seed, champions });
+    }
+    Ok(matches)
+}
+
+fn failable_main() -> Result<(), Box<dyn error::Error>> {
+    let _ = env_logger::init();
+    let options = parse_args(args().skip(1))?;
+    let config = VmConfig { semantics: options.semantics, ..VmConfig::default() };
+
+    let input: Box<dyn Read> = match options.batch_file {
+        Some(ref path) => Box::new(File::open(path)?),
+        None => Box::new(io::stdin()),
+    };
+    let lines = parse_matches(BufReader::new(input))?;
+
+    // every champion file is read once, however many matches it plays
+    let load_options = LoadOptions { max_size: config.champ_max_size, ..Default::default() };
+    let mut loaded = HashMap::new();
+    let mut matches = Vec::with_capacity(lines.len());
+    for line in &lines {
+        let mut champions = BTreeMap::new();
+        // players are numbered from 1 in the order of the line
+        for (number, path) in (1..).zip(&line.champions) {
+            if !loaded.contains_key(path) {
+                let champion = Champion::load(&mut File::open(path)?, load_options)
+                    .map_err(|e| format!("{}: {}", path, e))?;
+                loaded.insert(path.clone(), champion);
+            }
+            champions.insert(number, loaded[path].clone());
+        }
+
+        let seed = line.seed;
+        let strategy = match options.placement {
+            Placement::Even => PlacementStrategy::Even,
+            Placement::Random => PlacementStrategy::Random { seed },
+            Placement::RandomOrder => PlacementStrategy::RandomOrder { seed },
+        };
+        let mut spec = MatchSpec::new(champions, strategy);
+        spec.config = config;
+        spec.limits = options.limits;
+        spec.sample_interval = options.sample_interval;
+        matches.push(spec);
+    }
+
+    let threads = options.threads.unwrap_or_else(|| thread::available_parallelism().map_or(1, |n| n.get()));
+    let mut failed = false;
+    let stdout = io::stdout();
+    let mut stdout = stdout.lock();
+    // one json outcome per line, in the order of the batch file
+    for (line, outcome) in lines.iter().zip(run_batch(matches, threads)) {
+        match outcome {
+            Ok(outcome) => outcome.write_json(&mut stdout)?,
+            Err(err) => {
+                failed = true;
+                writeln!(stdout, "null")?;
+                eprintln!("match {} {}: {}", line.seed, line.champions.join(" "), err);
+            },
+        }
+    }
+
+    if failed {
+        return Err(Box::new(io::Error::other("some matches failed")))
+    }
+    Ok(())
+}
+
+fn main() {
+    if let Err(err) = failable_main() {
+        eprintln!("{}", err);
+        process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> impl Iterator<Item = String> + '_ {
+        line.split_whitespace().map(ToString::to_string)
+    }
+
+    #[test]
+    fn options() {
+        let options = parse_args(args("-j 8 --placement even --max-cycles 100 --sample-interval 50 batch.txt"));
+        let options = options.unwrap();
+        assert_eq!(options, Options {
+            threads: Some(8),
+            placement: Placement::Even,
+            semantics: Semantics::Native,
+            limits: Limits { max_cycles: Some(100), ..Limits::default() },
+            sample_interval: 50,
+            batch_file: Some("batch.txt".to_string()),
+        });
+        let options = parse_args(args("--reference -")).unwrap();
+        assert_eq!(options.placement, Placement::Random);
+        assert_eq!(options.semantics, Semantics::Reference);
+        assert_eq!(options.batch_file, None);
+
+        assert!(parse_args(args("-j")).is_err());
+        assert!(parse_args(args("-j all")).is_err());
+        assert!(parse_args(args("a.txt b.txt")).is_err());
+        assert!(parse_args(args("--json")).is_err());
+    }
+
+    #[test]
+    fn matches() {
+        let input = "# seed champions\n42 a.cor b.cor\n\n  7 a.cor b.cor c.cor d.cor\n";
+        assert_eq!(parse_matches(input.as_bytes()).unwrap(), [
+            MatchLine { seed: 42, champions: vec!["a.cor".to_string(), "b.cor".to_string()] },
+            MatchLine { seed: 7, champions: "a.cor b.cor c.cor d.cor".split(' ').map(ToString::to_string).collect() },
+        ]);
+        assert!(parse_matches("-1 a.cor".as_bytes()).is_err());
+        assert!(parse_matches("3".as_bytes()).is_err());
+    }
+}
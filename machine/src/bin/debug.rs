@@ -0,0 +1,383 @@
+extern crate env_logger;
+extern crate machine;
+
+use std::collections::{BTreeMap, HashMap};
+use std::env::args;
+use std::fs::File;
+use std::io::{self, BufRead, Write};
+use std::{process, error};
+use machine::Machine;
+use machine::champion::{Champion, LoadOptions};
+use machine::instruction::Instruction;
+use machine::instruction::mem_size::MemSize;
+use machine::breakpoint::{Breakpoint, Hit, Watchpoint};
+use machine::process::ProcessId;
+use machine::core::VmConfig;
+
+const HELP: &str = "\
+step                      execute one cycle
+step <pid>                execute until this process completes its current instruction
+next [N]                  execute N cycles, 1 by default
+continue                  execute until a breakpoint is hit or the match is over
+break                     list the breakpoints and watchpoints
+break pc <addr>           stop when an instruction at this address is executed
+break op <name|code>      stop when an instruction with this op code is executed
+break pid <pid>           stop when this process executes an instruction
+watch <addr> <len> [rw]   stop when this arena range is read (r) and/or written (w)
+clear                     remove every breakpoint and watchpoint
+regs <pid>                print the state of a process
+mem <addr> <len>          print the arena bytes
+disas <addr> [count]      disassemble the arena, 10 instructions by default
+procs                     list the living processes
+lives                     print the lives reported by each champion
+quit                      exit the debugger";
+
+const MNEMONICS: [&str; 16] = [
+    "live", "ld", "st", "add", "sub", "and", "or", "xor",
+    "zjmp", "ldi", "sti", "fork", "lld", "lldi", "lfork", "aff",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Command {
+    Step,
+    StepProcess(ProcessId),
+    Next(usize),
+    Continue,
+    ListBreakpoints,
+    Break(Breakpoint),
+    Watch(Watchpoint),
+    Clear,
+    Regs(ProcessId),
+    Mem(usize, usize),
+    Disas(usize, usize),
+    Procs,
+    Lives,
+    Help,
+    Quit,
+}
+
+fn parse_number(word: Option<&str>) -> Result<usize, String> {
+    let word = word.ok_or("missing number")?;
+    let result = match word.strip_prefix("0x") {
+        Some(hex) => usize::from_str_radix(hex, 16),
+        None => word.parse(),
+    };
+    result.map_err(|_| format!("invalid number {:?}", word))
+}
+
+fn parse_op_code(word: Option<&str>) -> Result<u8, String> {
+    let word = word.ok_or("missing op code")?;
+    match MNEMONICS.iter().position(|&m| m == word) {
+        Some(index) => Ok(index as u8 + 1),
+        None => match word.parse() {
+            Ok(op_code) if (1..=MNEMONICS.len() as u8).contains(&op_code) => Ok(op_code),
+            _ => Err(format!("unknown instruction {:?}", word)),
+        },
+    }
+}
+
+fn parse_command(line: &str) -> Result<Command, String> {
+    let mut words = line.split_whitespace();
+    let command = match words.next().ok_or("empty command")? {
+        "s" | "step" => match words.next() {
+            Some(pid) => Command::StepProcess(ProcessId(parse_number(Some(pid))?)),
+            None => Command::Step,
+        },
+        "n" | "next" => match words.next() {
+            Some(count) => Command::Next(parse_number(Some(count))?),
+            None => Command::Next(1),
+        },
+        "c" | "continue" => Command::Continue,
+        "b" | "break" => match words.next() {
+            None => Command::ListBreakpoints,
+            Some("pc") => Command::Break(Breakpoint::Pc(parse_number(words.next())?)),
+            Some("op") => Command::Break(Breakpoint::OpCode(parse_op_code(words.next())?)),
+            Some("pid") => Command::Break(Breakpoint::Process(ProcessId(parse_number(words.next())?))),
+            Some(kind) => return Err(format!("unknown breakpoint kind {:?}", kind)),
+        },
+        "w" | "watch" => {
+            let address = parse_number(words.next())?;
+            let len = parse_number(words.next())?;
+            let access = words.next().unwrap_or("rw");
+            if access.is_empty() || access.chars().any(|c| c != 'r' && c != 'w') {
+                return Err(format!("invalid access {:?}, expected r, w or rw", access))
+            }
+            let read = access.contains('r');
+            let write = access.contains('w');
+            Command::Watch(Watchpoint { address, len, read, write })
+        },
+        "clear" => Command::Clear,
+        "regs" => Command::Regs(ProcessId(parse_number(words.next())?)),
+        "x" | "mem" => Command::Mem(parse_number(words.next())?, parse_number(words.next())?),
+        "disas" => {
+            let address = parse_number(words.next())?;
+            let count = match words.next() {
+                Some(count) => parse_number(Some(count))?,
+                None => 10,
+            };
+            Command::Disas(address, count)
+        },
+        "procs" => Command::Procs,
+        "lives" => Command::Lives,
+        "h" | "help" => Command::Help,
+        "q" | "quit" => Command::Quit,
+        other => return Err(format!("unknown command {:?}, try help", other)),
+    };
+
+    match words.next() {
+        Some(word) => Err(format!("unexpected argument {:?}", word)),
+        None => Ok(command),
+    }
+}
+
+struct Debugger {
+    machine: Machine,
+    champions: Vec<(i32, String)>,
+    lives: HashMap<i32, usize>,
+    output: Vec<u8>,
+}
+
+impl Debugger {
+    /// Runs cycles until the predicate returns `true`, lives are counted for each cycle.
+    fn run<P: FnMut(&Machine) -> bool>(&mut self, mut predicate: P) -> io::Result<()> {
+        if self.machine.is_over() {
+            println!("the match is over");
+            return Ok(())
+        }
+
+        let lives = &mut self.lives;
+        let cycle_info = self.machine.run_until(&mut self.output, |machine, cycle_info| {
+            for (id, count) in &cycle_info.lives_counter {
+                *lives.entry(*id).or_insert(0) += count;
+            }
+            predicate(machine)
+        })?;
+
+        if !self.output.is_empty() {
+            println!("aff: {:?}", String::from_utf8_lossy(&self.output));
+            self.output.clear();
+        }
+
+        if let Some(cycle_info) = cycle_info {
+            for hit in &cycle_info.hits {
+                print_hit(hit);
+            }
+            println!("cycle {}, {} processes, cycles to die {}",
+                cycle_info.cycle, cycle_info.remaining_processes, cycle_info.cycles_to_die);
+        }
+        if self.machine.is_over() {
+            println!("the match is over");
+        }
+        Ok(())
+    }
+
+    fn execute(&mut self, command: Command) -> io::Result<()> {
+        match command {
+            Command::Step => self.run(|_| true)?,
+            Command::StepProcess(pid) => match self.machine.process(pid) {
+                Some(process) => {
+                    let cycle = process.next_cycle;
+                    self.run(|machine| machine.cycle() >= cycle)?
+                },
+                None => println!("no process with pid {}", pid),
+            },
+            Command::Next(count) => {
+                let cycle = self.machine.cycle() + count;
+                self.run(|machine| machine.cycle() >= cycle)?
+            },
+            Command::Continue => self.run(|_| false)?,
+            Command::ListBreakpoints => {
+                for breakpoint in self.machine.breakpoints().breakpoints() {
+                    println!("{:?}", breakpoint);
+                }
+                for watchpoint in self.machine.breakpoints().watchpoints() {
+                    println!("{:?}", watchpoint);
+                }
+            },
+            Command::Break(breakpoint) => self.machine.breakpoints_mut().add_breakpoint(breakpoint),
+            Command::Watch(watchpoint) => self.machine.breakpoints_mut().add_watchpoint(watchpoint),
+            Command::Clear => {
+                let breakpoints = self.machine.breakpoints_mut();
+                for breakpoint in breakpoints.breakpoints().to_vec() {
+                    breakpoints.remove_breakpoint(breakpoint);
+                }
+                for watchpoint in breakpoints.watchpoints().to_vec() {
+                    breakpoints.remove_watchpoint(watchpoint);
+                }
+            },
+            Command::Regs(pid) => match self.machine.process(pid) {
+                Some(process) => {
+                    let ctx = &process.context;
+                    let cycle = self.machine.cycle();
+                    println!("pid {} parent {:?} champion {}", ctx.pid, ctx.parent.map(|p| p.0), ctx.champion);
+                    println!("pc {:#06x} carry {} cycles since last live {}",
+                        ctx.pc.as_raw(), ctx.carry, cycle - ctx.last_live_cycle);
+                    match process.instruction {
+                        Some(instr) => println!("{} in {} cycles", instr, process.next_cycle - cycle),
+                        None => println!("invalid instruction in {} cycles", process.next_cycle - cycle),
+                    }
+                    for (i, value) in ctx.registers.as_slice().iter().enumerate() {
+                        println!("r{:<2} {:#010x} {}", i + 1, value, value);
+                    }
+                },
+                None => println!("no process with pid {}", pid),
+            },
+            Command::Mem(address, len) => {
+                let arena = self.machine.arena.as_slice();
+                for line in 0..len.div_ceil(16) {
+                    let start = address + line * 16;
+                    let bytes: Vec<_> = (start..start + 16).take(len - line * 16)
+                                            .map(|i| format!("{:02x}", arena[i % arena.len()]))
+                                            .collect();
+                    println!("{:#06x}: {}", start % arena.len(), bytes.join(" "));
+                }
+            },
+            Command::Disas(address, count) => {
+                let arena = &self.machine.arena;
+                let mut index = arena.index(address);
+                for _ in 0..count {
+                    match Instruction::read_from(arena.read_from(index)) {
+                        Ok(instr) => {
+                            println!("{:#06x}: {}", index.as_raw(), instr);
+                            index = index.advance_by(instr.mem_size());
+                        },
+                        Err(_) => {
+                            println!("{:#06x}: .byte {:#04x}", index.as_raw(), arena.as_slice()[index.as_raw()]);
+                            index = index.advance_by(1);
+                        },
+                    }
+                }
+            },
+            Command::Procs => {
+                println!("{:>6} {:>6} {:>8} {:>6} {:>6}  instruction", "pid", "parent", "champion", "pc", "wait");
+                for process in self.machine.processes() {
+                    let ctx = &process.context;
+                    let wait = process.next_cycle - self.machine.cycle();
+                    let parent = ctx.parent.map(|p| p.to_string()).unwrap_or_else(|| "-".to_string());
+                    let instr = process.instruction.map(|i| i.to_string()).unwrap_or_else(|| "-".to_string());
+                    println!("{:>6} {:>6} {:>8} {:>6} {:>6}  {}",
+                        ctx.pid, parent, ctx.champion, ctx.pc.as_raw(), wait, instr);
+                }
+            },
+            Command::Lives => {
+                let processes = self.machine.processes_per_champion();
+                for &(id, ref name) in &self.champions {
+                    let lives = self.lives.get(&id).cloned().unwrap_or(0);
+                    let processes = processes.get(&id).cloned().unwrap_or(0);
+                    println!("{} ({}): {} lives, {} processes", id, name, lives, processes);
+                }
+                match self.machine.last_living_champion() {
+                    Some((id, champion)) => println!("last living champion: {} ({})", id, champion.name),
+                    None => println!("no live reported yet"),
+                }
+            },
+            Command::Help => println!("{}", HELP),
+            Command::Quit => (),
+        }
+        Ok(())
+    }
+}
+
+fn print_hit(hit: &Hit) {
+    match *hit {
+        Hit::Breakpoint { breakpoint, pid, pc, instruction } => {
+            println!("{:?} hit by process {} at {:#06x}, before {}", breakpoint, pid, pc, instruction)
+        },
+        Hit::Watchpoint { watchpoint, pid, pc, instruction, address, access } => {
+            println!("{:?} hit by process {} at {:#06x}: {}, {:?} at {:#06x}",
+                watchpoint, pid, pc, instruction, access, address)
+        },
+    }
+}
+
+fn failable_main() -> Result<(), Box<dyn error::Error>> {
+    let _ = env_logger::init();
+    let config = VmConfig::default();
+
+    // players are numbered from 1 like in the reference VM
+    let enum_args = (1..).zip(args().skip(1));
+    let options = LoadOptions { max_size: config.champ_max_size, ..Default::default() };
+    let champions: Result<BTreeMap<i32, _>, Box<dyn error::Error>> = enum_args.map(|(id, path)| {
+            let mut file = File::open(&path)?;
+            println!("reading file at {}", path);
+            Ok((id, Champion::load(&mut file, options)?))
+        }).collect();
+    let champions = champions?;
+
+    let mut debugger = Debugger {
+        champions: champions.iter().map(|(id, champion)| (*id, champion.name.clone())).collect(),
+        machine: Machine::new(champions, config)?,
+        lives: HashMap::new(),
+        output: Vec::new(),
+    };
+
+    let stdin = io::stdin();
+    let mut last_command = None;
+    loop {
+        print!("(corewar) ");
+        io::stdout().flush()?;
+
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line)? == 0 {
+            break
+        }
+
+        let command = if line.trim().is_empty() {
+            match last_command {
+                Some(command) => command,
+                None => continue,
+            }
+        } else {
+            match parse_command(&line) {
+                Ok(command) => command,
+                Err(error) => {
+                    println!("{}", error);
+                    continue
+                },
+            }
+        };
+
+        if command == Command::Quit {
+            break
+        }
+        debugger.execute(command)?;
+        last_command = Some(command);
+    }
+
+    Ok(())
+}
+
+fn main() {
+    if let Err(err) = failable_main() {
+        eprintln!("{}", err);
+        process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_commands() {
+        assert_eq!(parse_command("step 2"), Ok(Command::StepProcess(ProcessId(2))));
+        assert_eq!(parse_command("next"), Ok(Command::Next(1)));
+        assert_eq!(parse_command("next 0x10"), Ok(Command::Next(16)));
+        assert_eq!(parse_command("break op fork"), Ok(Command::Break(Breakpoint::OpCode(12))));
+        assert_eq!(parse_command("break op 16"), Ok(Command::Break(Breakpoint::OpCode(16))));
+        assert_eq!(parse_command("break pid 3"), Ok(Command::Break(Breakpoint::Process(ProcessId(3)))));
+        assert_eq!(parse_command("watch 12 4 w"), Ok(Command::Watch(Watchpoint {
+            address: 12, len: 4, read: false, write: true,
+        })));
+        assert_eq!(parse_command("disas 100"), Ok(Command::Disas(100, 10)));
+    }
+
+    #[test]
+    fn parse_invalid_commands() {
+        assert!(parse_command("break op 17").is_err());
+        assert!(parse_command("watch 12 4 x").is_err());
+        assert!(parse_command("regs").is_err());
+        assert!(parse_command("procs 1").is_err());
+        assert!(parse_command("jump").is_err());
+    }
+}
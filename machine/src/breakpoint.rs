@@ -0,0 +1,198 @@
+use std::cell::RefCell;
+use std::mem;
+use instruction::Instruction;
+use process::ProcessId;
+
+/// Stops the machine before a process executes a matching instruction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Breakpoint {
+    /// Any instruction at this arena address.
+    Pc(usize),
+    /// Any instruction with this op code, `12` for every `fork` for example.
+    OpCode(u8),
+    /// Any instruction executed by this process.
+    Process(ProcessId),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Access {
+    Read,
+    Write,
+}
+
+/// Stops the machine when an instruction accesses this arena range,
+/// the range wraps around the arena.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Watchpoint {
+    pub address: usize,
+    pub len: usize,
+    pub read: bool,
+    pub write: bool,
+}
+
+impl Watchpoint {
+    fn matches(&self, access: Access, address: usize, len: usize, mem_size: usize) -> bool {
+        let watched = match access {
+            Access::Read => self.read,
+            Access::Write => self.write,
+        };
+        watched && (0..len).any(|i| {
+            let offset = (address + i + mem_size - self.address % mem_size) % mem_size;
+            offset < self.len
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hit {
+    Breakpoint {
+        breakpoint: Breakpoint,
+        pid: ProcessId,
+        pc: usize,
+        instruction: Instruction,
+    },
+    /// `address` is the first byte accessed by the instruction.
+    Watchpoint {
+        watchpoint: Watchpoint,
+        pid: ProcessId,
+        pc: usize,
+        instruction: Instruction,
+        address: usize,
+        access: Access,
+    },
+}
+
+/// The breakpoints and watchpoints of a machine and the ones hit during the current cycle.
+///
+/// Breakpoints are checked against the instructions about to be executed,
+/// watchpoints against the accesses of the instruction being executed.
+///
+/// Hits are recorded behind a `RefCell` because reads are checked
+/// from `GetValue`, which only has a shared access to the machine.
+#[derive(Debug, Default)]
+pub struct Breakpoints {
+    breakpoints: Vec<Breakpoint>,
+    /// The breakpoints added since the instructions about to be executed were checked.
+    unchecked: Vec<Breakpoint>,
+    watchpoints: Vec<Watchpoint>,
+    executing: Option<(ProcessId, usize, Instruction)>,
+    hits: RefCell<Vec<Hit>>,
+}
+
+impl Breakpoints {
+    pub fn breakpoints(&self) -> &[Breakpoint] {
+        &self.breakpoints
+    }
+
+    pub fn watchpoints(&self) -> &[Watchpoint] {
+        &self.watchpoints
+    }
+
+    pub fn add_breakpoint(&mut self, breakpoint: Breakpoint) {
+        if !self.breakpoints.contains(&breakpoint) {
+            self.breakpoints.push(breakpoint);
+            self.unchecked.push(breakpoint)
+        }
+    }
+
+    /// Returns `false` if the breakpoint didn't exist.
+    pub fn remove_breakpoint(&mut self, breakpoint: Breakpoint) -> bool {
+        let len = self.breakpoints.len();
+        self.breakpoints.retain(|b| *b != breakpoint);
+        self.unchecked.retain(|b| *b != breakpoint);
+        len != self.breakpoints.len()
+    }
+
+    pub fn add_watchpoint(&mut self, watchpoint: Watchpoint) {
+        if !self.watchpoints.contains(&watchpoint) {
+            self.watchpoints.push(watchpoint)
+        }
+    }
+
+    /// Returns `false` if the watchpoint didn't exist.
+    pub fn remove_watchpoint(&mut self, watchpoint: Watchpoint) -> bool {
+        let len = self.watchpoints.len();
+        self.watchpoints.retain(|w| *w != watchpoint);
+        len != self.watchpoints.len()
+    }
+
+    /// Returns `true` if breakpoints have been added since `mark_checked` was last called.
+    pub fn has_unchecked(&self) -> bool {
+        !self.unchecked.is_empty()
+    }
+
+    /// Tells that the instructions about to be executed have been checked.
+    pub fn mark_checked(&mut self) {
+        self.unchecked.clear()
+    }
+
+    /// Records the breakpoints hit by an instruction that has not been executed yet.
+    pub fn check(&mut self, pid: ProcessId, pc: usize, instruction: Instruction) {
+        Self::check_with(&self.breakpoints, self.hits.get_mut(), pid, pc, instruction)
+    }
+
+    /// Same as `check` with the breakpoints added since `mark_checked` was last called.
+    pub fn check_unchecked(&mut self, pid: ProcessId, pc: usize, instruction: Instruction) {
+        Self::check_with(&self.unchecked, self.hits.get_mut(), pid, pc, instruction)
+    }
+
+    fn check_with(breakpoints: &[Breakpoint], hits: &mut Vec<Hit>,
+                  pid: ProcessId, pc: usize, instruction: Instruction)
+    {
+        for &breakpoint in breakpoints {
+            let hit = match breakpoint {
+                Breakpoint::Pc(address) => address == pc,
+                Breakpoint::OpCode(op_code) => op_code == instruction.op_code(),
+                Breakpoint::Process(id) => id == pid,
+            };
+            if hit {
+                hits.push(Hit::Breakpoint { breakpoint, pid, pc, instruction })
+            }
+        }
+    }
+
+    /// Records the instruction being executed, its accesses are checked against the watchpoints.
+    pub fn enter(&mut self, pid: ProcessId, pc: usize, instruction: Instruction) {
+        if !self.watchpoints.is_empty() {
+            self.executing = Some((pid, pc, instruction));
+        }
+    }
+
+    pub fn leave(&mut self) {
+        self.executing = None
+    }
+
+    /// Records the watchpoints hit by an access of the executing instruction.
+    pub fn access(&self, access: Access, address: usize, len: usize, mem_size: usize) {
+        let (pid, pc, instruction) = match self.executing {
+            Some(executing) => executing,
+            None => return,
+        };
+        for &watchpoint in &self.watchpoints {
+            if watchpoint.matches(access, address, len, mem_size) {
+                let hit = Hit::Watchpoint { watchpoint, pid, pc, instruction, address, access };
+                self.hits.borrow_mut().push(hit)
+            }
+        }
+    }
+
+    pub fn take_hits(&mut self) -> Vec<Hit> {
+        mem::take(self.hits.get_mut())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn watchpoint_wraps_around() {
+        let watchpoint = Watchpoint { address: 62, len: 4, read: true, write: false };
+        assert!(watchpoint.matches(Access::Read, 0, 4, 64));
+        assert!(watchpoint.matches(Access::Read, 1, 4, 64));
+        assert!(watchpoint.matches(Access::Read, 59, 4, 64));
+        assert!(!watchpoint.matches(Access::Read, 2, 4, 64));
+        assert!(!watchpoint.matches(Access::Read, 58, 4, 64));
+        assert!(!watchpoint.matches(Access::Write, 62, 4, 64));
+    }
+}
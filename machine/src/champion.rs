@@ -1,43 +1,194 @@
-use std::io::{self, Read, Error, ErrorKind};
-use std::{mem, str};
-use core::{Header, COREWAR_EXEC_MAGIC};
+use std::io::{self, Read, Write};
+use std::{error, fmt};
+use core::{Header, HeaderError, CHAMP_MAX_SIZE};
 use program::Program;
 
+#[derive(Debug)]
+pub enum LoadError {
+    Io(io::Error),
+    Header(HeaderError),
+    ProgramTooLarge { size: usize, max_size: usize },
+    TruncatedProgram { prog_size: usize, actual_size: usize },
+    TrailingBytes { prog_size: usize },
+}
+
+impl From<io::Error> for LoadError {
+    fn from(error: io::Error) -> Self {
+        LoadError::Io(error)
+    }
+}
+
+impl From<HeaderError> for LoadError {
+    fn from(error: HeaderError) -> Self {
+        match error {
+            HeaderError::Io(e) => LoadError::Io(e),
+            error => LoadError::Header(error),
+        }
+    }
+}
+
+impl error::Error for LoadError {}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            LoadError::Io(ref e) => e.fmt(f),
+            LoadError::Header(ref e) => write!(f, "invalid header: {}", e),
+            LoadError::ProgramTooLarge { size, max_size } => {
+                write!(f, "program size is too long, {} exceeds {}", size, max_size)
+            },
+            LoadError::TruncatedProgram { prog_size, actual_size } => {
+                write!(f, "program is truncated, header announces {} bytes but only {} are present",
+                       prog_size, actual_size)
+            },
+            LoadError::TrailingBytes { prog_size } => {
+                write!(f, "program has trailing bytes, header announces only {} bytes", prog_size)
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LoadOptions {
+    /// The maximum program size accepted.
+    pub max_size: usize,
+    /// Rejects files with bytes after the announced program size.
+    pub strict: bool,
+}
+
+impl Default for LoadOptions {
+    fn default() -> Self {
+        LoadOptions { max_size: CHAMP_MAX_SIZE, strict: false }
+    }
+}
+
 #[derive(Debug, Clone)]
+#[non_exhaustive]
 pub struct Champion {
     pub name: String,
     pub comment: String,
     pub program: Program,
-    _private: (),
 }
 
 impl Champion {
-    pub fn new<R: Read>(reader: &mut R) -> io::Result<Self> {
-        let header: Header = unsafe {
-            let mut header = [0u8; mem::size_of::<Header>()];
-            reader.read_exact(&mut header)?;
-            mem::transmute(header)
+    pub fn new<R: Read>(reader: &mut R) -> Result<Self, LoadError> {
+        Champion::load(reader, LoadOptions::default())
+    }
+
+    pub fn load<R: Read>(reader: &mut R, options: LoadOptions) -> Result<Self, LoadError> {
+        let header = Header::read_from(reader)?;
+        let prog_size = header.prog_size as usize;
+        let program = Program::with_max_size(prog_size, options.max_size, reader)?;
+
+        // a single byte past the program is enough to reject the file
+        if options.strict {
+            let mut trailing = Vec::with_capacity(1);
+            reader.take(1).read_to_end(&mut trailing)?;
+            if !trailing.is_empty() {
+                return Err(LoadError::TrailingBytes { prog_size })
+            }
+        }
+
+        info!("champion \"{}\": \"{}\" loaded", header.prog_name, header.comment);
+
+        Ok(Champion {
+            name: header.prog_name,
+            comment: header.comment,
+            program,
+        })
+    }
+
+    /// Writes the champion as a `.cor` file.
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> Result<(), HeaderError> {
+        let header = Header {
+            prog_name: self.name.clone(),
+            prog_size: self.program.as_slice().len() as u32,
+            comment: self.comment.clone(),
+        };
+        header.write_to(writer)?;
+        writer.write_all(self.program.as_slice())?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::HEADER_SIZE;
+
+    fn cor_file(prog_size: u32, program: &[u8]) -> Vec<u8> {
+        let header = Header {
+            prog_name: "zork".to_string(),
+            prog_size,
+            comment: "I'M ALIIIIVE".to_string(),
         };
+        let mut output = Vec::new();
+        header.write_to(&mut output).unwrap();
+        output.extend_from_slice(program);
+        output
+    }
+
+    #[test]
+    fn valid() {
+        let file = cor_file(3, &[16, 1, 42]);
+        let champion = Champion::load(&mut file.as_slice(), LoadOptions { strict: true, ..Default::default() }).unwrap();
 
-        if header.magic.to_be() != COREWAR_EXEC_MAGIC {
-            return Err(Error::new(ErrorKind::InvalidData, "invalid magic number"))
+        assert_eq!(champion.name, "zork");
+        assert_eq!(champion.program.as_slice(), &[16, 1, 42]);
+    }
+
+    #[test]
+    fn invalid_header() {
+        let file = cor_file(3, &[16, 1, 42]);
+        match Champion::new(&mut &file[..HEADER_SIZE - 1]) {
+            Err(LoadError::Header(HeaderError::InvalidSize(size))) => assert_eq!(size, HEADER_SIZE - 1),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn too_large() {
+        let file = cor_file(CHAMP_MAX_SIZE as u32 + 1, &[]);
+        match Champion::new(&mut file.as_slice()) {
+            Err(LoadError::ProgramTooLarge { size, max_size }) => {
+                assert_eq!(size, CHAMP_MAX_SIZE + 1);
+                assert_eq!(max_size, CHAMP_MAX_SIZE);
+            },
+            other => panic!("unexpected {:?}", other),
         }
+    }
 
-        fn into_str_nul_trimmed(slice: &[u8]) -> &str {
-            let name = unsafe { str::from_utf8_unchecked(slice) };
-            name.trim_right_matches(|c| c == '\0')
+    #[test]
+    fn truncated() {
+        let file = cor_file(5, &[16, 1, 42]);
+        match Champion::new(&mut file.as_slice()) {
+            Err(LoadError::TruncatedProgram { prog_size: 5, actual_size: 3 }) => (),
+            other => panic!("unexpected {:?}", other),
         }
+    }
 
-        let name = into_str_nul_trimmed(&header.prog_name);
-        let comment = into_str_nul_trimmed(&header.comment);
+    #[test]
+    fn trailing_bytes() {
+        let file = cor_file(2, &[16, 1, 42]);
+        assert!(Champion::new(&mut file.as_slice()).is_ok());
 
-        info!("champion \"{}\": \"{}\" loaded", name, comment);
+        let strict = LoadOptions { strict: true, ..Default::default() };
+        match Champion::load(&mut file.as_slice(), strict) {
+            Err(LoadError::TrailingBytes { prog_size: 2 }) => (),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
 
-        Ok(Champion {
-            name: name.to_string(),
-            comment: comment.to_string(),
-            program: Program::new(header.prog_size.to_be() as usize, reader)?,
-            _private: (),
-        })
+    #[test]
+    fn strict_loading_reads_one_byte_past_the_program() {
+        let mut file = cor_file(3, &[16, 1, 42]);
+        file.extend_from_slice(&[0; 100]);
+        let mut reader = file.as_slice();
+        let strict = LoadOptions { strict: true, ..Default::default() };
+        match Champion::load(&mut reader, strict) {
+            Err(LoadError::TrailingBytes { prog_size: 3 }) => (),
+            other => panic!("unexpected {:?}", other),
+        }
+        assert_eq!(reader.len(), 99);
     }
 }
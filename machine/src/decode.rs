@@ -0,0 +1,93 @@
+use std::io::{self, Read};
+use instruction::Instruction;
+
+/// The instructions decoded at each arena address,
+/// each one is kept until a byte it was read from is written.
+#[derive(Debug, Clone)]
+pub struct DecodeCache {
+    entries: Vec<Option<Entry>>,
+    /// The most bytes read to decode a cached instruction.
+    longest: usize,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Entry {
+    /// `None` if the bytes don't form a valid instruction.
+    instruction: Option<Instruction>,
+    len: usize,
+}
+
+impl DecodeCache {
+    pub fn new(mem_size: usize) -> Self {
+        DecodeCache { entries: vec![None; mem_size], longest: 0 }
+    }
+
+    /// Returns the instruction decoded at `index`, if it is cached.
+    pub fn get(&self, index: usize) -> Option<Option<Instruction>> {
+        self.entries[index].map(|entry| entry.instruction)
+    }
+
+    /// Decodes the instruction read by `reader` and caches it at `index`.
+    pub fn decode<R: Read>(&mut self, index: usize, reader: R) -> Option<Instruction> {
+        let mut reader = CountingReader { inner: reader, count: 0 };
+        let instruction = Instruction::read_from(&mut reader).ok();
+        self.longest = self.longest.max(reader.count);
+        self.entries[index] = Some(Entry { instruction, len: reader.count });
+        instruction
+    }
+
+    /// Forgets the instructions read from the byte at `index`.
+    pub fn invalidate(&mut self, index: usize) {
+        let size = self.entries.len();
+        for back in 0..self.longest.min(size) {
+            let start = (index + size - back) % size;
+            if self.entries[start].is_some_and(|entry| entry.len > back) {
+                self.entries[start] = None;
+            }
+        }
+    }
+}
+
+struct CountingReader<R> {
+    inner: R,
+    count: usize,
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.count += read;
+        Ok(read)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use instruction::parameter::{AltDirect, Direct};
+    use test_support::assemble;
+
+    #[test]
+    fn invalidates_instructions_covering_the_byte() {
+        let live = Instruction::Live(Direct(-1));
+        let zjmp = Instruction::ZJump(AltDirect(-5));
+        let memory = assemble(&[live, zjmp]);
+
+        let mut cache = DecodeCache::new(memory.len());
+        assert_eq!(cache.decode(0, &memory[..]), Some(live));
+        assert_eq!(cache.decode(5, &memory[5..]), Some(zjmp));
+        // 0xff is not an opcode
+        assert_eq!(cache.decode(6, &memory[6..]), None);
+        assert_eq!(cache.get(6), Some(None));
+
+        cache.invalidate(4);
+        assert_eq!(cache.get(0), None);
+        assert_eq!(cache.get(5), Some(Some(zjmp)));
+        assert_eq!(cache.get(6), Some(None));
+
+        // the invalid opcode at 6 is the only byte read
+        cache.invalidate(7);
+        assert_eq!(cache.get(5), None);
+        assert_eq!(cache.get(6), Some(None));
+    }
+}
@@ -1,5 +1,6 @@
 pub mod parameter;
 pub mod mem_size;
+pub mod reference;
 mod get_value;
 mod set_value;
 mod write_to;
@@ -13,10 +14,12 @@ use self::mem_size::MemSize;
 use self::write_to::WriteTo;
 use self::get_value::GetValue;
 use self::set_value::SetValue;
-use arena::ArenaIndex;
-use machine::Machine;
+use machine::{live_champion_id, Machine};
 use process::Context;
-use core::IDX_MOD;
+use observer::Event;
+use arena::ArenaIndex;
+use verbosity::Verbosity;
+use core::Semantics;
 
 pub const OP_CODE_SIZE:     usize = 1;
 pub const PARAM_CODE_SIZE:  usize = 1;
@@ -359,7 +362,7 @@ impl Instruction {
     }
 
     pub fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
-        writer.write(&[self.op_code()])?;
+        writer.write_all(&[self.op_code()])?;
         match *self {
             Live(dir) => {
                 dir.write_to(writer)?;
@@ -510,127 +513,262 @@ impl Instruction {
         }
     }
 
+    /// Returns the highest register number used by this instruction,
+    /// `0` if it doesn't use any register.
+    pub fn highest_register(&self) -> u8 {
+        let registers = match *self {
+            Live(a) => [a.register(), None, None],
+            Load(a, b) => [a.register(), b.register(), None],
+            Store(a, b) => [a.register(), b.register(), None],
+            Addition(a, b, c) => [a.register(), b.register(), c.register()],
+            Substraction(a, b, c) => [a.register(), b.register(), c.register()],
+            And(a, b, c) => [a.register(), b.register(), c.register()],
+            Or(a, b, c) => [a.register(), b.register(), c.register()],
+            Xor(a, b, c) => [a.register(), b.register(), c.register()],
+            ZJump(a) => [a.register(), None, None],
+            LoadIndex(a, b, c) => [a.register(), b.register(), c.register()],
+            StoreIndex(a, b, c) => [a.register(), b.register(), c.register()],
+            Fork(a) => [a.register(), None, None],
+            LongLoad(a, b) => [a.register(), b.register(), None],
+            LongLoadIndex(a, b, c) => [a.register(), b.register(), c.register()],
+            LongFork(a) => [a.register(), None, None],
+            Display(a) => [a.register(), None, None],
+        };
+        registers.iter().filter_map(|reg| reg.map(|reg| *reg)).max().unwrap_or(0)
+    }
+
     pub fn execute<W: Write>(&self, machine: &mut Machine, context: &mut Context, output: &mut W)
         -> io::Result<()> {
+        self.execute_encoded(self.mem_size(), machine, context, output)
+    }
+
+    /// Executes the instruction as if it took `size` bytes in the arena,
+    /// the reference encoding is not always as long as the native one.
+    pub fn execute_encoded<W: Write>(&self,
+                                     size: usize,
+                                     machine: &mut Machine,
+                                     context: &mut Context,
+                                     output: &mut W) -> io::Result<()>
+    {
+        machine.breakpoints_mut().enter(context.pid, context.pc.as_raw(), *self);
+        let result = self.execute_operation(size, machine, context, output);
+        // later accesses must not be blamed on this instruction, even if it failed
+        machine.breakpoints_mut().leave();
+        result
+    }
 
+    fn execute_operation<W: Write>(&self,
+                                   size: usize,
+                                   machine: &mut Machine,
+                                   context: &mut Context,
+                                   output: &mut W) -> io::Result<()>
+    {
+        let reference = machine.config().semantics == Semantics::Reference;
+        let pc = context.pc;
+        let idx_mod = machine.config().idx_mod as isize;
         match *self {
-            Live(Direct(champion_id)) => {
-                context.cycle_since_last_live = 0;
-                machine.live_champion(champion_id);
-                context.pc = context.pc.advance_by(self.mem_size());
+            Live(Direct(argument)) => {
+                trace_operation(machine, context, format_args!("live {}", argument))?;
+                context.last_live_cycle = machine.cycle();
+                machine.live_champion(live_champion_id(argument))?;
+                context.pc = context.pc.advance_by(size);
             },
             Load(dir_ind, reg) => {
                 let value = dir_ind.get_value(machine, context);
+                trace_operation(machine, context, format_args!("ld {} {}", value, reg))?;
                 context.registers[reg] = value;
-                context.carry = { value == 0 };
-                context.pc = context.pc.advance_by(self.mem_size());
+                context.carry = value == 0;
+                context.pc = context.pc.advance_by(size);
             },
             Store(reg, ind_reg) => {
                 let value = context.registers[reg];
+                let target = match ind_reg {
+                    IndReg::Indirect(ind) => ind.0 as i32,
+                    IndReg::Register(reg) => *reg as i32,
+                };
+                trace_operation(machine, context, format_args!("st {} {}", reg, target))?;
                 match ind_reg {
                     IndReg::Indirect(ind) => ind.set_value(value, machine, context),
                     IndReg::Register(reg) => context.registers[reg] = value,
                 }
-                context.pc = context.pc.advance_by(self.mem_size());
+                context.pc = context.pc.advance_by(size);
             },
             Addition(reg_a, reg_b, reg_c) => {
+                trace_operation(machine, context, format_args!("add {} {} {}", reg_a, reg_b, reg_c))?;
                 let val_a = context.registers[reg_a];
                 let val_b = context.registers[reg_b];
                 let result = val_a.wrapping_add(val_b);
                 context.registers[reg_c] = result;
-                context.carry = { result == 0 };
-                context.pc = context.pc.advance_by(self.mem_size());
+                context.carry = result == 0;
+                context.pc = context.pc.advance_by(size);
             },
             Substraction(reg_a, reg_b, reg_c) => {
+                trace_operation(machine, context, format_args!("sub {} {} {}", reg_a, reg_b, reg_c))?;
                 let val_a = context.registers[reg_a];
                 let val_b = context.registers[reg_b];
                 let result = val_a.wrapping_sub(val_b);
                 context.registers[reg_c] = result;
-                context.carry = { result == 0 };
-                context.pc = context.pc.advance_by(self.mem_size());
+                context.carry = result == 0;
+                context.pc = context.pc.advance_by(size);
             },
             And(dir_ind_reg_a, dir_ind_reg_b, reg) => {
                 let val_a = dir_ind_reg_a.get_value(machine, context);
                 let val_b = dir_ind_reg_b.get_value(machine, context);
+                trace_operation(machine, context, format_args!("and {} {} {}", val_a, val_b, reg))?;
                 let result = val_a & val_b;
                 context.registers[reg] = result;
-                context.carry = { result == 0 };
-                context.pc = context.pc.advance_by(self.mem_size());
+                context.carry = result == 0;
+                context.pc = context.pc.advance_by(size);
             },
             Or(dir_ind_reg_a, dir_ind_reg_b, reg) => {
                 let val_a = dir_ind_reg_a.get_value(machine, context);
                 let val_b = dir_ind_reg_b.get_value(machine, context);
+                trace_operation(machine, context, format_args!("or {} {} {}", val_a, val_b, reg))?;
                 let result = val_a | val_b;
                 context.registers[reg] = result;
-                context.carry = { result == 0 };
-                context.pc = context.pc.advance_by(self.mem_size());
+                context.carry = result == 0;
+                context.pc = context.pc.advance_by(size);
             },
             Xor(dir_ind_reg_a, dir_ind_reg_b, reg) => {
                 let val_a = dir_ind_reg_a.get_value(machine, context);
                 let val_b = dir_ind_reg_b.get_value(machine, context);
+                trace_operation(machine, context, format_args!("xor {} {} {}", val_a, val_b, reg))?;
                 let result = val_a ^ val_b;
                 context.registers[reg] = result;
-                context.carry = { result == 0 };
-                context.pc = context.pc.advance_by(self.mem_size());
-            },
-            ZJump(AltDirect(alt_dir)) => if context.carry {
-                context.pc = context.pc.move_by(alt_dir as isize % IDX_MOD as isize);
-            } else {
-                context.pc = context.pc.advance_by(self.mem_size());
+                context.carry = result == 0;
+                context.pc = context.pc.advance_by(size);
+            },
+            ZJump(AltDirect(alt_dir)) => {
+                let status = if context.carry { "OK" } else { "FAILED" };
+                trace_operation(machine, context, format_args!("zjmp {} {}", alt_dir, status))?;
+                if context.carry {
+                    context.pc = context.pc.move_by(alt_dir as isize % idx_mod);
+                } else {
+                    context.pc = context.pc.advance_by(size);
+                }
             },
             LoadIndex(dir_ind_reg, dir_reg, reg) => {
                 let val_a = dir_ind_reg.get_value(machine, context);
                 let val_b = dir_reg.get_value(machine, context);
-                let addr = Indirect(val_a.wrapping_add(val_b) as i16);
+                let offset = index_offset(val_a, val_b, idx_mod, reference);
+                trace_operation(machine, context, format_args!(
+                    "ldi {} {} {}\n       | -> load from {} + {} = {} (with pc and mod {})",
+                    val_a, val_b, reg, val_a, val_b, offset, pc.as_raw() as isize + offset as isize % idx_mod))?;
+                let addr = Indirect(offset);
                 context.registers[reg] = addr.get_value(machine, context);
-                context.pc = context.pc.advance_by(self.mem_size());
+                context.pc = context.pc.advance_by(size);
             },
             StoreIndex(reg, dir_ind_reg, dir_reg) => {
                 let value = context.registers[reg];
                 let val_a = dir_ind_reg.get_value(machine, context);
                 let val_b = dir_reg.get_value(machine, context);
-                let addr = Indirect(val_a.wrapping_add(val_b) as i16);
+                let offset = index_offset(val_a, val_b, idx_mod, reference);
+                trace_operation(machine, context, format_args!(
+                    "sti {} {} {}\n       | -> store to {} + {} = {} (with pc and mod {})",
+                    reg, val_a, val_b, val_a, val_b, offset, pc.as_raw() as isize + offset as isize % idx_mod))?;
+                let addr = Indirect(offset);
                 addr.set_value(value, machine, context);
-                context.pc = context.pc.advance_by(self.mem_size());
+                context.pc = context.pc.advance_by(size);
             },
             Fork(AltDirect(alt_dir)) => {
-                let mut fork = context.clean_fork();
-                fork.pc = fork.pc.move_by(alt_dir as isize % IDX_MOD as isize);
+                let target = pc.as_raw() as isize + alt_dir as isize % idx_mod;
+                trace_operation(machine, context, format_args!("fork {} ({})", alt_dir, target))?;
+                let mut fork = context.clean_fork(machine.next_process_id(), machine.cycle());
+                fork.pc = fork.pc.move_by(alt_dir as isize % idx_mod);
                 machine.new_process(fork);
-                context.pc = context.pc.advance_by(self.mem_size());
+                context.pc = context.pc.advance_by(size);
             },
             LongLoad(dir_ind, reg) => {
                 let value = dir_ind.get_value_long(machine, context);
+                trace_operation(machine, context, format_args!("lld {} {}", value, reg))?;
                 context.registers[reg] = value;
-                context.carry = true; // ???
-                context.pc = context.pc.advance_by(self.mem_size());
+                context.carry = if reference { value == 0 } else { true }; // ???
+                context.pc = context.pc.advance_by(size);
             },
             LongLoadIndex(dir_ind_reg, dir_reg, reg) => {
                 let val_a = dir_ind_reg.get_value_long(machine, context);
                 let val_b = dir_reg.get_value_long(machine, context);
-                let addr = Indirect(val_a.wrapping_add(val_b) as i16);
-                context.registers[reg] = addr.get_value_long(machine, context);
-                context.carry = { context.pc != ArenaIndex::zero() };
-                context.pc = context.pc.advance_by(self.mem_size());
+                let offset = val_a.wrapping_add(val_b) as i16;
+                trace_operation(machine, context, format_args!(
+                    "lldi {} {} {}\n       | -> load from {} + {} = {} (with pc {})",
+                    val_a, val_b, reg, val_a, val_b, offset, pc.as_raw() as isize + offset as isize))?;
+                let addr = Indirect(offset);
+                let value = addr.get_value_long(machine, context);
+                context.registers[reg] = value;
+                context.carry = if reference { value == 0 } else { context.pc.as_raw() != 0 };
+                context.pc = context.pc.advance_by(size);
             },
             LongFork(AltDirect(alt_dir)) => {
-                let mut fork = context.clean_fork();
+                let target = pc.as_raw() as isize + alt_dir as isize;
+                trace_operation(machine, context, format_args!("lfork {} ({})", alt_dir, target))?;
+                let mut fork = context.clean_fork(machine.next_process_id(), machine.cycle());
                 fork.pc = fork.pc.move_by(alt_dir as isize);
                 machine.new_process(fork);
-                context.pc = context.pc.advance_by(self.mem_size());
+                context.pc = context.pc.advance_by(size);
             },
             Display(reg) => {
+                trace_operation(machine, context, format_args!("aff {}", reg))?;
                 let value = context.registers[reg] as u8;
                 output.write_all(&[value])?;
-                context.pc = context.pc.advance_by(self.mem_size());
+                machine.notify(Event::Aff { pid: context.pid, byte: value });
+                context.pc = context.pc.advance_by(size);
             },
         }
+
+        let jumped = match *self {
+            ZJump(_) => context.carry,
+            _ => false,
+        };
+        if !jumped {
+            trace_pc_movement(machine, pc, size)?;
+        }
         Ok(())
     }
 
     pub fn execute_noop(context: &mut Context) {
         context.pc = context.pc.advance_by(1)
     }
+
+    /// Skips an instruction with an invalid param code or register
+    /// like the reference VM does.
+    pub fn skip(machine: &mut Machine, context: &mut Context, size: usize) -> io::Result<()> {
+        trace_pc_movement(machine, context.pc, size)?;
+        context.pc = context.pc.advance_by(size);
+        Ok(())
+    }
+}
+
+/// Returns the offset read or written by `ldi` and `sti`, the reference VM
+/// applies the modulo before the sum is truncated to an indirect.
+fn index_offset(val_a: i32, val_b: i32, idx_mod: isize, reference: bool) -> i16 {
+    let sum = val_a.wrapping_add(val_b);
+    if reference { (sum % idx_mod as i32) as i16 } else { sum as i16 }
+}
+
+/// Writes the line of an operation, process ids start at one like in the reference VM.
+fn trace_operation(machine: &mut Machine, context: &Context, args: fmt::Arguments) -> io::Result<()> {
+    match machine.trace(Verbosity::OPERATIONS) {
+        Some(writer) => writeln!(writer, "P {:4} | {}", context.pid.0 + 1, args),
+        None => Ok(()),
+    }
+}
+
+/// Writes the bytes of the `len` bytes long instruction skipped from `pc`.
+fn trace_pc_movement(machine: &mut Machine, pc: ArenaIndex, len: usize) -> io::Result<()> {
+    if !machine.verbosity().contains(Verbosity::PC_MOVEMENTS) {
+        return Ok(())
+    }
+    let mut bytes = vec![0; len];
+    machine.arena.read_from(pc).read_exact(&mut bytes)?;
+
+    let mut line = format!("ADV {} ({:#06x} -> {:#06x}) ", len, pc.as_raw(), pc.as_raw() + len);
+    for byte in bytes {
+        line.push_str(&format!("{:02x} ", byte));
+    }
+    match machine.trace(Verbosity::PC_MOVEMENTS) {
+        Some(writer) => writeln!(writer, "{}", line),
+        None => Ok(()),
+    }
 }
 
 impl HasParamCode for Instruction {
@@ -2,6 +2,7 @@ use std::io::{self, Read, Write};
 use std::fmt;
 use instruction::parameter::{AltDirect, Indirect};
 use instruction::parameter::{ParamType, ParamTypeOf};
+use instruction::parameter::{Register, RegisterOf};
 use instruction::parameter::InvalidParamType;
 use instruction::parameter::ind_reg::Error;
 use instruction::mem_size::MemSize;
@@ -51,6 +52,12 @@ impl MemSize for AltDirInd {
     }
 }
 
+impl RegisterOf for AltDirInd {
+    fn register(&self) -> Option<Register> {
+        None
+    }
+}
+
 impl WriteTo for AltDirInd {
     fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
         match *self {
@@ -1,7 +1,7 @@
 use std::io::{self, Read, Write};
 use std::fmt;
 use instruction::parameter::{AltDirect, Indirect};
-use instruction::parameter::{ParamType, ParamTypeOf};
+use instruction::parameter::{ParamType, ParamTypeOf, RegisterOf};
 use instruction::parameter::Register;
 use instruction::parameter::dir_ind_reg::Error;
 use instruction::mem_size::MemSize;
@@ -65,6 +65,15 @@ impl ParamTypeOf for AltDirIndReg {
     }
 }
 
+impl RegisterOf for AltDirIndReg {
+    fn register(&self) -> Option<Register> {
+        match *self {
+            AltDirIndReg::Register(register) => Some(register),
+            _ => None,
+        }
+    }
+}
+
 impl WriteTo for AltDirIndReg {
     fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
         match *self {
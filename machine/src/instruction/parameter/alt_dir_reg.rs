@@ -1,7 +1,7 @@
 use std::io::{self, Read, Write};
 use std::fmt;
 use instruction::parameter::{AltDirect, Register};
-use instruction::parameter::{ParamType, ParamTypeOf};
+use instruction::parameter::{ParamType, ParamTypeOf, RegisterOf};
 use instruction::parameter::InvalidParamType;
 use instruction::parameter::dir_reg::Error;
 use instruction::mem_size::MemSize;
@@ -53,6 +53,15 @@ impl ParamTypeOf for AltDirReg {
     }
 }
 
+impl RegisterOf for AltDirReg {
+    fn register(&self) -> Option<Register> {
+        match *self {
+            AltDirReg::Register(register) => Some(register),
+            _ => None,
+        }
+    }
+}
+
 impl WriteTo for AltDirReg {
     fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
         match *self {
@@ -2,6 +2,7 @@ use std::io::{self, Read, Write};
 use std::fmt;
 use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
 use instruction::parameter::ALT_DIR_SIZE;
+use instruction::parameter::{Register, RegisterOf};
 use instruction::mem_size::ConstMemSize;
 use instruction::write_to::WriteTo;
 use instruction::get_value::GetValue;
@@ -29,6 +30,12 @@ impl GetValue for AltDirect {
     }
 }
 
+impl RegisterOf for AltDirect {
+    fn register(&self) -> Option<Register> {
+        None
+    }
+}
+
 impl WriteTo for AltDirect {
     fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
         writer.write_i16::<BigEndian>(self.0)
@@ -2,6 +2,7 @@ use std::io::{self, Read, Write};
 use std::fmt;
 use instruction::parameter::{Direct, Indirect};
 use instruction::parameter::{ParamType, ParamTypeOf};
+use instruction::parameter::{Register, RegisterOf};
 use instruction::parameter::InvalidParamType;
 use instruction::mem_size::MemSize;
 use instruction::write_to::WriteTo;
@@ -62,6 +63,12 @@ impl MemSize for DirInd {
     }
 }
 
+impl RegisterOf for DirInd {
+    fn register(&self) -> Option<Register> {
+        None
+    }
+}
+
 impl WriteTo for DirInd {
     fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
         match *self {
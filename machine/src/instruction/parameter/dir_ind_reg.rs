@@ -1,7 +1,7 @@
 use std::io::{self, Read, Write};
 use std::fmt;
 use instruction::parameter::{Direct, Indirect};
-use instruction::parameter::{ParamType, ParamTypeOf};
+use instruction::parameter::{ParamType, ParamTypeOf, RegisterOf};
 use instruction::parameter::{Register, RegisterError, InvalidRegister};
 use instruction::mem_size::MemSize;
 use instruction::write_to::WriteTo;
@@ -91,6 +91,15 @@ impl ParamTypeOf for DirIndReg {
     }
 }
 
+impl RegisterOf for DirIndReg {
+    fn register(&self) -> Option<Register> {
+        match *self {
+            DirIndReg::Register(register) => Some(register),
+            _ => None,
+        }
+    }
+}
+
 impl WriteTo for DirIndReg {
     fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
         match *self {
@@ -1,7 +1,7 @@
 use std::io::{self, Read, Write};
 use std::fmt;
 use instruction::parameter::{Direct, Register, RegisterError, InvalidRegister};
-use instruction::parameter::{ParamType, ParamTypeOf};
+use instruction::parameter::{ParamType, ParamTypeOf, RegisterOf};
 use instruction::parameter::InvalidParamType;
 use instruction::mem_size::MemSize;
 use instruction::write_to::WriteTo;
@@ -80,6 +80,15 @@ impl ParamTypeOf for DirReg {
     }
 }
 
+impl RegisterOf for DirReg {
+    fn register(&self) -> Option<Register> {
+        match *self {
+            DirReg::Register(register) => Some(register),
+            _ => None,
+        }
+    }
+}
+
 impl WriteTo for DirReg {
     fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
         match *self {
@@ -2,6 +2,7 @@ use std::io::{self, Read, Write};
 use std::fmt;
 use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
 use instruction::parameter::DIR_SIZE;
+use instruction::parameter::{Register, RegisterOf};
 use instruction::mem_size::ConstMemSize;
 use instruction::write_to::WriteTo;
 use instruction::get_value::GetValue;
@@ -29,6 +30,12 @@ impl GetValue for Direct {
     }
 }
 
+impl RegisterOf for Direct {
+    fn register(&self) -> Option<Register> {
+        None
+    }
+}
+
 impl WriteTo for Direct {
     fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
         writer.write_i32::<BigEndian>(self.0)
@@ -1,7 +1,7 @@
 use std::io::{self, Read, Write};
 use std::fmt;
 use instruction::parameter::{Indirect, Register, RegisterError, InvalidRegister};
-use instruction::parameter::{ParamType, ParamTypeOf};
+use instruction::parameter::{ParamType, ParamTypeOf, RegisterOf};
 use instruction::parameter::InvalidParamType;
 use instruction::mem_size::MemSize;
 use instruction::write_to::WriteTo;
@@ -87,6 +87,15 @@ impl ParamTypeOf for IndReg {
     }
 }
 
+impl RegisterOf for IndReg {
+    fn register(&self) -> Option<Register> {
+        match *self {
+            IndReg::Register(register) => Some(register),
+            _ => None,
+        }
+    }
+}
+
 impl WriteTo for IndReg {
     fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
         match *self {
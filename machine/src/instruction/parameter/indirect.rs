@@ -1,14 +1,17 @@
 use std::io::{self, Read, Write};
 use std::fmt;
-use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use byteorder::{BigEndian, ByteOrder, ReadBytesExt, WriteBytesExt};
 use instruction::parameter::IND_SIZE;
+use instruction::parameter::{Register, RegisterOf};
 use instruction::mem_size::ConstMemSize;
 use instruction::write_to::WriteTo;
 use instruction::get_value::GetValue;
 use instruction::set_value::SetValue;
 use machine::Machine;
+use arena::ArenaIndex;
+use observer::Event;
+use breakpoint::Access;
 use process::Context;
-use core::IDX_MOD;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Indirect(pub i16);
@@ -27,29 +30,43 @@ impl ConstMemSize for Indirect {
 
 impl GetValue for Indirect {
     fn get_value(&self, vm: &Machine, context: &Context) -> i32 {
-        let addr = context.pc.move_by(self.0 as isize % IDX_MOD as isize);
-        let mut reader = vm.arena.read_from(addr);
-        reader.read_i32::<BigEndian>().unwrap()
+        let addr = context.pc.move_by(self.0 as isize % vm.config().idx_mod as isize);
+        vm.watch(Access::Read, addr, 4);
+        read_i32(vm, addr)
     }
 
     fn get_value_long(&self, vm: &Machine, context: &Context) -> i32 {
         let addr = context.pc.move_by(self.0 as isize);
-        let mut reader = vm.arena.read_from(addr);
-        reader.read_i32::<BigEndian>().unwrap()
+        vm.watch(Access::Read, addr, 4);
+        read_i32(vm, addr)
     }
 }
 
+fn read_i32(vm: &Machine, addr: ArenaIndex) -> i32 {
+    let mut bytes = [0; 4];
+    vm.arena.read_bytes(addr, &mut bytes);
+    BigEndian::read_i32(&bytes)
+}
+
 impl SetValue for Indirect {
     fn set_value(&self, value: i32, vm: &mut Machine, context: &Context) {
-        let addr = context.pc.move_by(self.0 as isize % IDX_MOD as isize);
-        let mut writer = vm.arena.write_to(addr);
-        writer.write_i32::<BigEndian>(value).unwrap();
+        let addr = context.pc.move_by(self.0 as isize % vm.config().idx_mod as isize);
+        vm.watch(Access::Write, addr, 4);
+        let mut bytes = [0; 4];
+        BigEndian::write_i32(&mut bytes, value);
+        vm.arena.write_bytes(addr, context.champion, &bytes);
+        vm.notify(Event::MemoryWritten {
+            pid: context.pid,
+            champion: context.champion,
+            address: addr.as_raw(),
+            len: 4,
+        });
     }
+}
 
-    fn set_value_long(&self, value: i32, vm: &mut Machine, context: &Context) {
-        let addr = context.pc.move_by(self.0 as isize);
-        let mut writer = vm.arena.write_to(addr);
-        writer.write_i32::<BigEndian>(value).unwrap();
+impl RegisterOf for Indirect {
+    fn register(&self) -> Option<Register> {
+        None
     }
 }
 
@@ -52,6 +52,10 @@ pub trait ParamTypeOf {
     fn param_type(&self) -> ParamType;
 }
 
+pub trait RegisterOf {
+    fn register(&self) -> Option<Register>;
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct InvalidParamType(ParamType);
 
@@ -113,7 +117,7 @@ impl ParamCode {
             ParamNumber::First => (self.0 & 0b1100_0000) >> 6,
             ParamNumber::Second => (self.0 & 0b0011_0000) >> 4,
             ParamNumber::Third => (self.0 & 0b0000_1100) >> 2,
-            ParamNumber::Fourth => (self.0 & 0b0000_0011) >> 0,
+            ParamNumber::Fourth => self.0 & 0b0000_0011,
         };
         match param_type {
             0b10 => Ok(ParamType::Direct),
@@ -155,7 +159,7 @@ impl ParamCodeBuilder {
 
     pub fn fourth<P: ParamTypeOf>(self, param: &P) -> Self {
         let param_type = ParamTypeOf::param_type(param);
-        ParamCodeBuilder((self.0 & 0b1111_1100) | Into::<u8>::into(param_type) << 0)
+        ParamCodeBuilder((self.0 & 0b1111_1100) | Into::<u8>::into(param_type))
     }
 }
 
@@ -1,7 +1,7 @@
 use std::io::{self, Read, Write};
 use std::{ops, fmt};
 use byteorder::{ReadBytesExt, WriteBytesExt};
-use instruction::parameter::REG_SIZE;
+use instruction::parameter::{REG_SIZE, RegisterOf};
 use instruction::mem_size::ConstMemSize;
 use instruction::write_to::WriteTo;
 use instruction::get_value::GetValue;
@@ -32,7 +32,7 @@ impl From<InvalidRegister> for Error {
 
 impl fmt::Display for InvalidRegister {
     fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
-        write!(f, "invalid register number")
+        write!(f, "invalid register number {}", self.0)
     }
 }
 
@@ -42,7 +42,7 @@ pub struct Register(u8);
 impl Register {
     pub fn new(number: u8) -> Result<Self, InvalidRegister> {
         match number {
-            number @ 1...REG_MAX => Ok(Register(number)),
+            number @ 1..=REG_MAX => Ok(Register(number)),
             number => Err(InvalidRegister(number)),
         }
     }
@@ -72,6 +72,12 @@ impl GetValue for Register {
     }
 }
 
+impl RegisterOf for Register {
+    fn register(&self) -> Option<Register> {
+        Some(*self)
+    }
+}
+
 impl WriteTo for Register {
     fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
         writer.write_u8(self.0)
@@ -0,0 +1,288 @@
+//! Decoding following the official VM, the opcode is latched when a process
+//! starts waiting and the rest of the instruction is read when it is executed.
+//!
+//! The encoding differs from the native one: every instruction except
+//! `live`, `zjmp`, `fork` and `lfork` has a param code, `add`, `sub` and `aff` included,
+//! and every field of the param code is checked.
+
+use std::io::{self, Read};
+use byteorder::{BigEndian, ReadBytesExt};
+use instruction::{OP_CODE_SIZE, PARAM_CODE_SIZE};
+use instruction::Instruction::{self, *};
+use instruction::parameter::*;
+
+/// An instruction decoded from its latched opcode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Decoded {
+    /// `size` is the number of bytes the instruction takes in the arena.
+    Valid { instruction: Instruction, size: usize },
+    /// The param code or a register is invalid, the instruction isn't executed
+    /// and the process skips the `size` bytes announced by the param code.
+    Invalid { size: usize },
+    /// Not an opcode, the process moves to the next byte.
+    InvalidOpCode,
+}
+
+struct Op {
+    params: usize,
+    param_code: bool,
+    dir_size: usize,
+    cycle_cost: usize,
+}
+
+const fn op(params: usize, param_code: bool, dir_size: usize, cycle_cost: usize) -> Op {
+    Op { params, param_code, dir_size, cycle_cost }
+}
+
+/// Indexed by opcode minus one.
+const OPS: [Op; 16] = [
+    op(1, false, 4, 10),   // live
+    op(2, true, 4, 5),     // ld
+    op(2, true, 4, 5),     // st
+    op(3, true, 4, 10),    // add
+    op(3, true, 4, 10),    // sub
+    op(3, true, 4, 6),     // and
+    op(3, true, 4, 6),     // or
+    op(3, true, 4, 6),     // xor
+    op(1, false, 2, 20),   // zjmp
+    op(3, true, 2, 25),    // ldi
+    op(3, true, 2, 25),    // sti
+    op(1, false, 2, 800),  // fork
+    op(2, true, 4, 10),    // lld
+    op(3, true, 2, 50),    // lldi
+    op(1, false, 2, 1000), // lfork
+    op(1, true, 4, 2),     // aff
+];
+
+fn op_of(op_code: u8) -> Option<&'static Op> {
+    (op_code as usize).checked_sub(1).and_then(|index| OPS.get(index))
+}
+
+/// Returns the number of cycles a process waits before executing this opcode.
+pub fn cycle_cost(op_code: u8) -> Option<usize> {
+    op_of(op_code).map(|op| op.cycle_cost)
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Arg {
+    Reg(Register),
+    Dir(i32),
+    Ind(i16),
+}
+
+/// Decodes the instruction starting at the opcode byte the reader is on,
+/// the opcode byte itself is skipped in favor of the latched `op_code`.
+///
+/// Registers above `reg_number` are invalid.
+pub fn decode<R: Read>(op_code: u8, mut reader: R, reg_number: usize) -> io::Result<Decoded> {
+    let op = match op_of(op_code) {
+        Some(op) => op,
+        None => return Ok(Decoded::InvalidOpCode),
+    };
+    reader.read_u8()?;
+
+    let mut types = [None; 3];
+    let mut size = OP_CODE_SIZE;
+    if op.param_code {
+        let param_code = ParamCode::read_from(&mut reader)?;
+        size += PARAM_CODE_SIZE;
+        let numbers = [ParamNumber::First, ParamNumber::Second, ParamNumber::Third];
+        for (param_type, number) in types.iter_mut().zip(&numbers).take(op.params) {
+            *param_type = param_code.param_type_of(*number).ok();
+        }
+    } else {
+        types[0] = Some(ParamType::Direct);
+    }
+
+    for param_type in types.iter().take(op.params) {
+        size += match *param_type {
+            Some(ParamType::Register) => 1,
+            Some(ParamType::Indirect) => 2,
+            Some(ParamType::Direct) => op.dir_size,
+            None => 0,
+        };
+    }
+
+    let mut args = [None; 3];
+    for (arg, param_type) in args.iter_mut().zip(&types).take(op.params) {
+        *arg = match *param_type {
+            Some(ParamType::Register) => {
+                let number = reader.read_u8()?;
+                if number as usize > reg_number {
+                    return Ok(Decoded::Invalid { size })
+                }
+                match Register::new(number) {
+                    Ok(reg) => Some(Arg::Reg(reg)),
+                    Err(_) => return Ok(Decoded::Invalid { size }),
+                }
+            },
+            Some(ParamType::Indirect) => Some(Arg::Ind(reader.read_i16::<BigEndian>()?)),
+            Some(ParamType::Direct) if op.dir_size == 2 => Some(Arg::Dir(reader.read_i16::<BigEndian>()? as i32)),
+            Some(ParamType::Direct) => Some(Arg::Dir(reader.read_i32::<BigEndian>()?)),
+            None => return Ok(Decoded::Invalid { size }),
+        };
+    }
+
+    Ok(match build(op_code, args) {
+        Some(instruction) => Decoded::Valid { instruction, size },
+        None => Decoded::Invalid { size },
+    })
+}
+
+/// Returns `None` if an argument has a type the instruction doesn't accept.
+fn build(op_code: u8, args: [Option<Arg>; 3]) -> Option<Instruction> {
+    let [a, b, c] = args;
+    Some(match op_code {
+        1 => Live(direct(a?)?),
+        2 => Load(dir_ind(a?)?, register(b?)?),
+        3 => Store(register(a?)?, ind_reg(b?)?),
+        4 => Addition(register(a?)?, register(b?)?, register(c?)?),
+        5 => Substraction(register(a?)?, register(b?)?, register(c?)?),
+        6 => And(dir_ind_reg(a?)?, dir_ind_reg(b?)?, register(c?)?),
+        7 => Or(dir_ind_reg(a?)?, dir_ind_reg(b?)?, register(c?)?),
+        8 => Xor(dir_ind_reg(a?)?, dir_ind_reg(b?)?, register(c?)?),
+        9 => ZJump(alt_direct(a?)?),
+        10 => LoadIndex(alt_dir_ind_reg(a?)?, alt_dir_reg(b?)?, register(c?)?),
+        11 => StoreIndex(register(a?)?, alt_dir_ind_reg(b?)?, alt_dir_reg(c?)?),
+        12 => Fork(alt_direct(a?)?),
+        13 => LongLoad(dir_ind(a?)?, register(b?)?),
+        14 => LongLoadIndex(alt_dir_ind_reg(a?)?, alt_dir_reg(b?)?, register(c?)?),
+        15 => LongFork(alt_direct(a?)?),
+        16 => Display(register(a?)?),
+        _ => return None,
+    })
+}
+
+fn register(arg: Arg) -> Option<Register> {
+    match arg {
+        Arg::Reg(reg) => Some(reg),
+        _ => None,
+    }
+}
+
+fn direct(arg: Arg) -> Option<Direct> {
+    match arg {
+        Arg::Dir(value) => Some(Direct(value)),
+        _ => None,
+    }
+}
+
+fn alt_direct(arg: Arg) -> Option<AltDirect> {
+    match arg {
+        Arg::Dir(value) => Some(AltDirect(value as i16)),
+        _ => None,
+    }
+}
+
+fn dir_ind(arg: Arg) -> Option<DirInd> {
+    match arg {
+        Arg::Dir(value) => Some(DirInd::Direct(Direct(value))),
+        Arg::Ind(value) => Some(DirInd::Indirect(Indirect(value))),
+        Arg::Reg(_) => None,
+    }
+}
+
+fn ind_reg(arg: Arg) -> Option<IndReg> {
+    match arg {
+        Arg::Reg(reg) => Some(IndReg::Register(reg)),
+        Arg::Ind(value) => Some(IndReg::Indirect(Indirect(value))),
+        Arg::Dir(_) => None,
+    }
+}
+
+fn dir_ind_reg(arg: Arg) -> Option<DirIndReg> {
+    match arg {
+        Arg::Reg(reg) => Some(DirIndReg::Register(reg)),
+        Arg::Dir(value) => Some(DirIndReg::Direct(Direct(value))),
+        Arg::Ind(value) => Some(DirIndReg::Indirect(Indirect(value))),
+    }
+}
+
+fn alt_dir_ind_reg(arg: Arg) -> Option<AltDirIndReg> {
+    match arg {
+        Arg::Reg(reg) => Some(AltDirIndReg::Register(reg)),
+        Arg::Dir(value) => Some(AltDirIndReg::AltDirect(AltDirect(value as i16))),
+        Arg::Ind(value) => Some(AltDirIndReg::Indirect(Indirect(value))),
+    }
+}
+
+fn alt_dir_reg(arg: Arg) -> Option<AltDirReg> {
+    match arg {
+        Arg::Reg(reg) => Some(AltDirReg::Register(reg)),
+        Arg::Dir(value) => Some(AltDirReg::AltDirect(AltDirect(value as i16))),
+        Arg::Ind(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::REG_NUMBER;
+
+    fn decode_bytes(bytes: &[u8]) -> Decoded {
+        decode(bytes[0], bytes, REG_NUMBER).unwrap()
+    }
+
+    fn reg(number: u8) -> Register {
+        Register::new(number).unwrap()
+    }
+
+    /// Instructions as assembled by the official `asm`, with their size.
+    #[test]
+    fn reference_corpus() {
+        let corpus: &[(&[u8], Instruction)] = &[
+            // zork.s
+            (&[0x0b, 0x68, 0x01, 0x00, 0x0f, 0x00, 0x01], StoreIndex(reg(1), AltDirIndReg::AltDirect(AltDirect(15)), AltDirReg::AltDirect(AltDirect(1)))),
+            (&[0x06, 0x64, 0x01, 0x00, 0x00, 0x00, 0x00, 0x01], And(DirIndReg::Register(reg(1)), DirIndReg::Direct(Direct(0)), reg(1))),
+            (&[0x01, 0x00, 0x00, 0x00, 0x01], Live(Direct(1))),
+            (&[0x09, 0xff, 0xfb], ZJump(AltDirect(-5))),
+            // st r1, 6 and st r1, r2
+            (&[0x03, 0x70, 0x01, 0x00, 0x06], Store(reg(1), IndReg::Indirect(Indirect(6)))),
+            (&[0x03, 0x50, 0x01, 0x02], Store(reg(1), IndReg::Register(reg(2)))),
+            // add r2, r3, r4 and aff r1, both with a param code
+            (&[0x04, 0x54, 0x02, 0x03, 0x04], Addition(reg(2), reg(3), reg(4))),
+            (&[0x10, 0x40, 0x01], Display(reg(1))),
+            // ld -4, r3 and lld %42, r3
+            (&[0x02, 0xd0, 0xff, 0xfc, 0x03], Load(DirInd::Indirect(Indirect(-4)), reg(3))),
+            (&[0x0d, 0x90, 0x00, 0x00, 0x00, 0x2a, 0x03], LongLoad(DirInd::Direct(Direct(42)), reg(3))),
+            // ldi 3, %4, r1 and lldi r2, r3, r4
+            (&[0x0a, 0xe4, 0x00, 0x03, 0x00, 0x04, 0x01], LoadIndex(AltDirIndReg::Indirect(Indirect(3)), AltDirReg::AltDirect(AltDirect(4)), reg(1))),
+            (&[0x0e, 0x54, 0x02, 0x03, 0x04], LongLoadIndex(AltDirIndReg::Register(reg(2)), AltDirReg::Register(reg(3)), reg(4))),
+            // fork %-12 and lfork %4096
+            (&[0x0c, 0xff, 0xf4], Fork(AltDirect(-12))),
+            (&[0x0f, 0x10, 0x00], LongFork(AltDirect(4096))),
+        ];
+
+        for &(bytes, instruction) in corpus {
+            assert_eq!(decode_bytes(bytes), Decoded::Valid { instruction, size: bytes.len() }, "{:02x?}", bytes);
+            assert_eq!(cycle_cost(bytes[0]), Some(instruction.cycle_cost()));
+        }
+    }
+
+    #[test]
+    fn invalid_param_codes_skip_announced_arguments() {
+        // ld with a register as first argument: 2 + 1 + 1
+        assert_eq!(decode_bytes(&[0x02, 0x50, 0x01, 0x02]), Decoded::Invalid { size: 4 });
+        // sti with an empty third field: 2 + 1 + 2
+        assert_eq!(decode_bytes(&[0x0b, 0x60, 0x01, 0x00, 0x0f]), Decoded::Invalid { size: 5 });
+        // and with only empty fields
+        assert_eq!(decode_bytes(&[0x06, 0x00]), Decoded::Invalid { size: 2 });
+        // the unused fields are ignored
+        assert_eq!(decode_bytes(&[0x10, 0x43, 0x01]), Decoded::Valid { instruction: Display(reg(1)), size: 3 });
+    }
+
+    #[test]
+    fn invalid_registers_skip_the_instruction() {
+        assert_eq!(decode_bytes(&[0x04, 0x54, 0x02, 0x00, 0x04]), Decoded::Invalid { size: 5 });
+        assert_eq!(decode_bytes(&[0x04, 0x54, 0x02, 0x11, 0x04]), Decoded::Invalid { size: 5 });
+        assert_eq!(decode(0x10, &[0x10, 0x40, 0x05][..], 4).unwrap(), Decoded::Invalid { size: 3 });
+    }
+
+    #[test]
+    fn invalid_op_codes() {
+        assert_eq!(decode_bytes(&[0x00]), Decoded::InvalidOpCode);
+        assert_eq!(decode_bytes(&[0x11, 0x00]), Decoded::InvalidOpCode);
+        assert_eq!(cycle_cost(0), None);
+        assert_eq!(cycle_cost(17), None);
+    }
+}
@@ -0,0 +1,51 @@
+use std::collections::VecDeque;
+use process::Process;
+use arena::ArenaWrite;
+
+/// What is needed to undo one cycle.
+#[derive(Debug, Default)]
+pub struct CycleRecord {
+    pub last_living_champion: Option<i32>,
+    pub next_pid: usize,
+    pub rejected_forks: usize,
+    pub number_of_lives: usize,
+    pub cycles_to_die: usize,
+    pub cycles: usize,
+    pub cycle_checks: usize,
+    /// The processes killed at the cycle to die check with their slot, in the order they were removed.
+    pub killed: Vec<(usize, Process)>,
+    /// The processes that executed an instruction with their slot, as they were before the cycle.
+    pub executed: Vec<(usize, Process)>,
+    pub writes: Vec<ArenaWrite>,
+}
+
+/// Keeps the records of the last `capacity` cycles, the oldest ones are dropped.
+#[derive(Debug)]
+pub struct Journal {
+    capacity: usize,
+    records: VecDeque<CycleRecord>,
+}
+
+impl Journal {
+    pub fn new(capacity: usize) -> Self {
+        Journal { capacity, records: VecDeque::new() }
+    }
+
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn push(&mut self, record: CycleRecord) {
+        if self.capacity == 0 {
+            return
+        }
+        if self.records.len() == self.capacity {
+            self.records.pop_front();
+        }
+        self.records.push_back(record)
+    }
+
+    pub fn pop(&mut self) -> Option<CycleRecord> {
+        self.records.pop_back()
+    }
+}
@@ -1,5 +1,3 @@
-#![feature(const_size_of)]
-
 extern crate byteorder;
 #[macro_use] extern crate log;
 pub extern crate core;
@@ -8,7 +6,26 @@ mod machine;
 pub mod champion;
 pub mod program;
 pub mod arena;
-mod process;
+pub mod process;
 pub mod instruction;
+pub mod observer;
+pub mod breakpoint;
+pub mod snapshot;
+pub mod placement;
+pub mod verbosity;
+pub mod outcome;
+pub mod limits;
+pub mod batch;
+mod journal;
+mod slab;
+mod decode;
+#[cfg(any(test, feature = "bench-support"))]
+#[doc(hidden)]
+pub mod test_support;
 
-pub use machine::{Machine, CycleExecute};
+pub use machine::{Machine, CycleExecute, CycleInfo};
+pub use placement::{PlacementError, PlacementStrategy};
+pub use verbosity::Verbosity;
+pub use limits::Limits;
+pub use outcome::{MatchOutcome, MatchResult, OutcomeRecorder, Termination};
+pub use batch::{run_batch, BatchError, MatchSpec};
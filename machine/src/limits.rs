@@ -0,0 +1,12 @@
+use std::time::Duration;
+
+/// Resource ceilings of a match, nothing is limited by default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Limits {
+    /// The match ends once this many cycles have been executed.
+    pub max_cycles: Option<usize>,
+    /// Forks are rejected while this many processes are alive.
+    pub max_processes: Option<usize>,
+    /// The match ends once it has been running for this long.
+    pub max_duration: Option<Duration>,
+}
@@ -1,67 +1,403 @@
-use std::collections::{BTreeMap, HashMap};
-use std::io::{self, Write};
-use std::mem;
-use process::{Process, Context};
+use std::collections::{BTreeMap, BinaryHeap, HashMap};
+use std::cmp::Reverse;
+use std::io::{self, Read, Write};
+use std::time::Instant;
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use process::{Process, ProcessId, ProcessKey, Context};
+use slab::ProcessSlab;
 use instruction::parameter::{Direct, Register};
 use instruction::Instruction;
-use instruction::Error as InstrError;
-use champion::Champion;
+use instruction::reference::{self, Decoded};
+use champion::{Champion, LoadOptions};
 use arena::{Arena, ArenaIndex};
-use core::{MEM_SIZE, CYCLE_TO_DIE, CYCLE_DELTA, NBR_LIVE, MAX_CHECKS};
+use breakpoint::{Access, Breakpoints, Hit};
+use observer::{Event, MachineObserver};
+use journal::{Journal, CycleRecord};
+use placement::{self, PlacementError, PlacementStrategy};
+use verbosity::Verbosity;
+use limits::Limits;
+use outcome::Termination;
+use snapshot::{self, SnapshotError, SNAPSHOT_MAGIC, SNAPSHOT_VERSION, write_usize, read_usize};
+use core::{Semantics, VmConfig};
 
 pub struct Machine {
     pub arena: Arena,
+    config: VmConfig,
     champions: BTreeMap<i32, Champion>,
-    processes: Vec<Process>,
+    /// The processes alive, in slots reused once they die.
+    processes: ProcessSlab,
+    /// The next execution of each process with its slot, the earliest first.
+    queue: BinaryHeap<Reverse<(usize, ProcessKey, usize)>>,
+    process_counts: HashMap<i32, usize>,
+    next_pid: usize,
+    observers: Vec<Box<dyn MachineObserver + Send>>,
+    breakpoints: Breakpoints,
+    journal: Option<Journal>,
+    placement: Option<PlacementStrategy>,
+    trace: Option<(Verbosity, Box<dyn Write + Send>)>,
+    limits: Limits,
+    rejected_forks: usize,
     last_living_champion: Option<i32>,
 
     number_of_lives: usize,
     cycles_to_die: usize,
+    cycle: usize,
     cycles: usize,
     cycle_checks: usize,
 }
 
+/// Returns the player number a `live` reports alive, the opposite of its argument.
+pub fn live_champion_id(argument: i32) -> i32 {
+    argument.wrapping_neg()
+}
+
 impl Machine {
-    pub fn new(champions: BTreeMap<i32, Champion>) -> Self {
-        let mut arena = Arena::new();
-        let mut arena_index = ArenaIndex::zero();
-        let mut processes = Vec::with_capacity(champions.len());
-        let step = MEM_SIZE.checked_div(champions.len()).unwrap_or(0);
+    /// Loads the champions at evenly spaced addresses, in the order of their ids.
+    pub fn new(champions: BTreeMap<i32, Champion>, config: VmConfig) -> Result<Self, PlacementError> {
+        Machine::with_strategy(champions, PlacementStrategy::Even, config)
+    }
 
-        for (id, &Champion{ ref program, .. }) in &champions {
-            {
-                let mut writer = arena.write_to(arena_index);
-                io::copy(&mut program.as_slice(), &mut writer).unwrap();
-            }
+    /// Loads the champions at the addresses chosen by the strategy.
+    pub fn with_strategy(champions: BTreeMap<i32, Champion>,
+                         strategy: PlacementStrategy,
+                         config: VmConfig) -> Result<Self, PlacementError>
+    {
+        config.validate()?;
+        let placements = strategy.placements(&champions, config.mem_size)?;
+        placement::check_placements(&champions, &placements, config.mem_size)?;
+        Ok(Machine::load(champions, &placements, Some(strategy), config))
+    }
+
+    /// Loads each champion at the given address, every champion must have one
+    /// and programs must not overlap.
+    pub fn with_placements(champions: BTreeMap<i32, Champion>,
+                           placements: &BTreeMap<i32, usize>,
+                           config: VmConfig) -> Result<Self, PlacementError>
+    {
+        config.validate()?;
+        placement::check_placements(&champions, placements, config.mem_size)?;
+        Ok(Machine::load(champions, placements, None, config))
+    }
+
+    /// Returns the strategy used to load the champions,
+    /// `None` if they were loaded at explicit addresses.
+    pub fn placement_strategy(&self) -> Option<PlacementStrategy> {
+        self.placement
+    }
+
+    fn load(champions: BTreeMap<i32, Champion>,
+            placements: &BTreeMap<i32, usize>,
+            placement: Option<PlacementStrategy>,
+            config: VmConfig) -> Self
+    {
+        let mut arena = Arena::new(config.mem_size);
+        let mut processes = ProcessSlab::new();
+
+        for (pid, (id, Champion { program, .. })) in champions.iter().enumerate() {
+            let arena_index = arena.index(placements[id]);
+            arena.write_bytes(arena_index, *id, program.as_slice());
 
-            let mut context = Context::new(arena_index);
+            // like the reference VM, r1 holds the negated player number
+            let mut context = Context::new(ProcessId(pid), *id, arena_index, config.reg_number);
             let reg = Register::new(1).unwrap();
-            context.registers[reg] = *id;
+            context.registers[reg] = id.wrapping_neg();
 
-            let process = Process::new(context, &arena);
+            let process = Process::new(context, &mut arena, config.semantics, 0);
             trace!("push process {:?}", process);
-            processes.push(process);
-
-            arena_index = arena_index.advance_by(step);
+            processes.insert(process);
         }
 
-        Machine {
-            arena: arena,
-            champions: champions,
-            processes: processes,
+        let mut machine = Machine {
+            arena,
+            config,
+            champions,
+            next_pid: processes.len(),
+            processes,
+            queue: BinaryHeap::new(),
+            process_counts: HashMap::new(),
+            observers: Vec::new(),
+            breakpoints: Breakpoints::default(),
+            journal: None,
+            placement,
+            trace: None,
+            limits: Limits::default(),
+            rejected_forks: 0,
             last_living_champion: None,
             number_of_lives: 0,
-            cycles_to_die: CYCLE_TO_DIE,
+            cycles_to_die: config.cycle_to_die,
+            cycle: 0,
             cycles: 0,
             cycle_checks: 0,
+        };
+        machine.reschedule();
+        machine
+    }
+
+    /// Rebuilds the queue and the process counts from the processes.
+    fn reschedule(&mut self) {
+        self.queue = self.processes.iter().map(|(slot, p)| Reverse((p.next_cycle, p.key(), slot))).collect();
+        self.process_counts.clear();
+        for (_, process) in self.processes.iter() {
+            *self.process_counts.entry(process.context.champion).or_insert(0) += 1;
+        }
+    }
+
+    pub fn config(&self) -> &VmConfig {
+        &self.config
+    }
+
+    /// Writes the whole state of the match, observers and breakpoints are not part of it.
+    pub fn write_snapshot<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&SNAPSHOT_MAGIC)?;
+        writer.write_u16::<BigEndian>(SNAPSHOT_VERSION)?;
+
+        let config = self.config;
+        for value in &[config.mem_size, config.idx_mod, config.champ_max_size, config.reg_number,
+                       config.cycle_to_die, config.cycle_delta, config.nbr_live, config.max_checks] {
+            write_usize(writer, *value)?;
+        }
+        snapshot::write_semantics(writer, config.semantics)?;
+
+        write_usize(writer, self.champions.len())?;
+        for (id, champion) in &self.champions {
+            writer.write_i32::<BigEndian>(*id)?;
+            champion.write_to(writer).map_err(io::Error::other)?;
+        }
+
+        self.arena.write_snapshot(writer)?;
+
+        write_usize(writer, self.processes.len())?;
+        for process in self.processes() {
+            process.write_snapshot(writer)?;
+        }
+        write_usize(writer, self.next_pid)?;
+        snapshot::write_placement(writer, self.placement)?;
+        write_usize(writer, self.rejected_forks)?;
+
+        snapshot::write_option_i32(writer, self.last_living_champion)?;
+        for value in &[self.number_of_lives, self.cycles_to_die, self.cycle, self.cycles, self.cycle_checks] {
+            write_usize(writer, *value)?;
+        }
+        Ok(())
+    }
+
+    /// Reads a snapshot written by `write_snapshot`, the match resumes exactly where it was.
+    pub fn read_snapshot<R: Read>(reader: &mut R) -> Result<Self, SnapshotError> {
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if magic != SNAPSHOT_MAGIC {
+            return Err(SnapshotError::InvalidMagic(magic))
+        }
+        let version = reader.read_u16::<BigEndian>()?;
+        if version != SNAPSHOT_VERSION {
+            return Err(SnapshotError::UnsupportedVersion(version))
+        }
+
+        let config = VmConfig {
+            mem_size: read_usize(reader)?,
+            idx_mod: read_usize(reader)?,
+            champ_max_size: read_usize(reader)?,
+            reg_number: read_usize(reader)?,
+            cycle_to_die: read_usize(reader)?,
+            cycle_delta: read_usize(reader)?,
+            nbr_live: read_usize(reader)?,
+            max_checks: read_usize(reader)?,
+            semantics: snapshot::read_semantics(reader)?,
+        };
+        if config.validate().is_err() {
+            return Err(SnapshotError::Inconsistent("invalid config"))
+        }
+        if config.mem_size > snapshot::MAX_MEM_SIZE {
+            return Err(SnapshotError::Inconsistent("arena is too large"))
+        }
+
+        // the champions have been placed in the arena, they can't be larger
+        let mut champions = BTreeMap::new();
+        let max_size = config.champ_max_size.min(config.mem_size);
+        let options = LoadOptions { max_size, strict: false };
+        for _ in 0..read_usize(reader)? {
+            let id = reader.read_i32::<BigEndian>()?;
+            champions.insert(id, Champion::load(reader, options)?);
+        }
+
+        let arena = Arena::read_snapshot(reader, config.mem_size)?;
+
+        let process_count = read_usize(reader)?;
+        let mut processes = ProcessSlab::new();
+        let mut last_key = None;
+        for _ in 0..process_count {
+            let process = Process::read_snapshot(reader, config.mem_size)?;
+            if process.context.registers.len() != config.reg_number {
+                return Err(SnapshotError::Inconsistent("process has an invalid number of registers"))
+            }
+            if last_key.is_some_and(|key| key >= process.key()) {
+                return Err(SnapshotError::Inconsistent("processes are not in execution order"))
+            }
+            last_key = Some(process.key());
+            processes.insert(process);
+        }
+        let next_pid = read_usize(reader)?;
+        let placement = snapshot::read_placement(reader)?;
+        if processes.iter().any(|(_, p)| p.context.pid.0 >= next_pid) {
+            return Err(SnapshotError::Inconsistent("process id is not below the next process id"))
+        }
+
+        let mut machine = Machine {
+            arena,
+            config,
+            champions,
+            processes,
+            queue: BinaryHeap::new(),
+            process_counts: HashMap::new(),
+            next_pid,
+            observers: Vec::new(),
+            breakpoints: Breakpoints::default(),
+            journal: None,
+            placement,
+            trace: None,
+            limits: Limits::default(),
+            rejected_forks: read_usize(reader)?,
+            last_living_champion: snapshot::read_option_i32(reader)?,
+            number_of_lives: read_usize(reader)?,
+            cycles_to_die: read_usize(reader)?,
+            cycle: read_usize(reader)?,
+            cycles: read_usize(reader)?,
+            cycle_checks: read_usize(reader)?,
+        };
+        let cycle = machine.cycle;
+        if machine.processes().any(|p| p.next_cycle <= cycle || p.birth_cycle > cycle) {
+            return Err(SnapshotError::Inconsistent("process doesn't wait for a future cycle"))
+        }
+        if machine.processes().any(|p| p.context.last_live_cycle > cycle) {
+            return Err(SnapshotError::Inconsistent("process reported a live in the future"))
+        }
+        machine.reschedule();
+        Ok(machine)
+    }
+
+    /// Writes the trace lines of the given levels to `writer`.
+    pub fn set_verbosity(&mut self, verbosity: Verbosity, writer: Box<dyn Write + Send>) {
+        self.trace = Some((verbosity, writer))
+    }
+
+    pub fn verbosity(&self) -> Verbosity {
+        self.trace.as_ref().map_or(Verbosity::ESSENTIALS, |&(verbosity, _)| verbosity)
+    }
+
+    /// Returns the trace writer if the level is enabled.
+    pub fn trace(&mut self, level: Verbosity) -> Option<&mut dyn Write> {
+        match self.trace {
+            Some((verbosity, ref mut writer)) if verbosity.contains(level) => Some(&mut **writer),
+            _ => None,
+        }
+    }
+
+    pub fn add_observer(&mut self, observer: Box<dyn MachineObserver + Send>) {
+        self.observers.push(observer)
+    }
+
+    pub fn notify(&mut self, event: Event) {
+        for observer in &mut self.observers {
+            observer.notify(&event)
         }
     }
 
-    pub fn live_champion(&mut self, champion_id: i32) {
-        if self.champions.get(&champion_id).is_some() {
+    /// Records what is needed to undo each of the next `capacity` cycles,
+    /// older cycles are forgotten.
+    pub fn enable_journal(&mut self, capacity: usize) {
+        self.journal = Some(Journal::new(capacity));
+        self.arena.record_writes(true);
+    }
+
+    pub fn disable_journal(&mut self) {
+        self.journal = None;
+        self.arena.record_writes(false);
+    }
+
+    /// Returns the number of cycles that can be undone.
+    pub fn journal_len(&self) -> usize {
+        self.journal.as_ref().map_or(0, Journal::len)
+    }
+
+    /// Undoes the last executed cycle, returns `false` if it isn't in the journal.
+    ///
+    /// Observers are not notified and the talk output is not taken back.
+    pub fn step_back(&mut self) -> bool {
+        let record = match self.journal.as_mut().and_then(Journal::pop) {
+            Some(record) => record,
+            None => return false,
+        };
+
+        self.arena.undo_writes(&record.writes);
+
+        let cycle = self.cycle;
+        self.processes.retain(|p| p.birth_cycle != cycle);
+        for (slot, process) in record.executed {
+            self.processes.replace(slot, process);
+        }
+        for (slot, process) in record.killed.into_iter().rev() {
+            if let Some(process) = self.processes.restore(slot, process) {
+                self.processes.insert(process);
+            }
+        }
+
+        self.last_living_champion = record.last_living_champion;
+        self.next_pid = record.next_pid;
+        self.rejected_forks = record.rejected_forks;
+        self.number_of_lives = record.number_of_lives;
+        self.cycles_to_die = record.cycles_to_die;
+        self.cycles = record.cycles;
+        self.cycle_checks = record.cycle_checks;
+        self.cycle -= 1;
+        self.reschedule();
+        true
+    }
+
+    /// Undoes cycles until `cycle` is reached, returns `false`
+    /// and stops at the oldest cycle recorded if it isn't in the journal.
+    pub fn rewind_to(&mut self, cycle: usize) -> bool {
+        while self.cycle > cycle {
+            if !self.step_back() {
+                return false
+            }
+        }
+        self.cycle == cycle
+    }
+
+    pub fn breakpoints(&self) -> &Breakpoints {
+        &self.breakpoints
+    }
+
+    pub fn breakpoints_mut(&mut self) -> &mut Breakpoints {
+        &mut self.breakpoints
+    }
+
+    /// Checks the watchpoints against an access of `len` bytes
+    /// made by the instruction being executed.
+    pub fn watch(&self, access: Access, address: ArenaIndex, len: usize) {
+        self.breakpoints.access(access, address.as_raw(), len, self.arena.size())
+    }
+
+    /// Reports the champion alive, `champion_id` is its player number.
+    pub fn live_champion(&mut self, champion_id: i32) -> io::Result<()> {
+        if let Some(champion) = self.champions.get(&champion_id) {
             self.last_living_champion = Some(champion_id);
             self.number_of_lives += 1;
+            if let Some((verbosity, ref mut writer)) = self.trace {
+                if verbosity.contains(Verbosity::LIVES) {
+                    writeln!(writer, "Player {} ({}) is said to be alive", champion_id, champion.name)?;
+                }
+            }
         }
+        Ok(())
+    }
+
+    pub fn champion(&self, id: i32) -> Option<&Champion> {
+        self.champions.get(&id)
+    }
+
+    pub fn champions(&self) -> &BTreeMap<i32, Champion> {
+        &self.champions
     }
 
     pub fn last_living_champion(&self) -> Option<(i32, &Champion)> {
@@ -70,10 +406,72 @@ impl Machine {
             .map(|champ| (id, champ)))
     }
 
-    pub fn new_process(&mut self, context: Context) {
-        let process = Process::new(context, &self.arena);
+    /// Returns a process id that has never been used in this match.
+    pub fn next_process_id(&mut self) -> ProcessId {
+        let pid = ProcessId(self.next_pid);
+        self.next_pid += 1;
+        pid
+    }
+
+    /// Limits are not part of snapshots.
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.limits = limits
+    }
+
+    pub fn limits(&self) -> Limits {
+        self.limits
+    }
+
+    /// Returns the number of forks rejected because of the process limit.
+    pub fn rejected_forks(&self) -> usize {
+        self.rejected_forks
+    }
+
+    /// Returns the number of processes alive.
+    pub fn process_count(&self) -> usize {
+        self.processes.len()
+    }
+
+    /// Adds the process, returns `false` and drops it if the process limit is reached.
+    pub fn new_process(&mut self, context: Context) -> bool {
+        if self.limits.max_processes.is_some_and(|max| self.process_count() >= max) {
+            self.rejected_forks += 1;
+            self.notify(Event::ForkRejected {
+                pid: context.pid,
+                parent: context.parent,
+                champion: context.champion,
+            });
+            return false
+        }
+        self.notify(Event::ProcessSpawned {
+            pid: context.pid,
+            parent: context.parent,
+            champion: context.champion,
+            pc: context.pc.as_raw(),
+        });
+        let process = Process::new(context, &mut self.arena, self.config.semantics, self.cycle);
         trace!("push process {:?}", process);
-        self.processes.push(process)
+        let (next_cycle, key) = (process.next_cycle, process.key());
+        *self.process_counts.entry(process.context.champion).or_insert(0) += 1;
+        let slot = self.processes.insert(process);
+        self.queue.push(Reverse((next_cycle, key, slot)));
+        true
+    }
+
+    /// Returns the processes still alive, in the order they execute.
+    pub fn processes(&self) -> impl Iterator<Item = &Process> {
+        let mut processes: Vec<_> = self.processes.iter().map(|(_, p)| p).collect();
+        processes.sort_unstable_by_key(|p| p.key());
+        processes.into_iter()
+    }
+
+    pub fn process(&self, pid: ProcessId) -> Option<&Process> {
+        self.processes.iter().map(|(_, p)| p).find(|p| p.context.pid == pid)
+    }
+
+    /// Returns the number of processes alive for each champion.
+    pub fn processes_per_champion(&self) -> HashMap<i32, usize> {
+        self.process_counts.clone()
     }
 
     pub fn cycles_to_die(&self) -> usize {
@@ -83,6 +481,356 @@ impl Machine {
     pub fn cycle_execute<'a, W: Write>(&'a mut self, output: &'a mut W) -> CycleExecute<'a, W> {
         CycleExecute { machine: self, output }
     }
+
+    /// Returns the number of cycles executed since the beginning of the match.
+    pub fn cycle(&self) -> usize {
+        self.cycle
+    }
+
+    /// Returns `true` once every process is dead.
+    pub fn is_over(&self) -> bool {
+        self.processes.is_empty()
+    }
+
+    /// Executes exactly one cycle, or none if a breakpoint added since the previous cycle
+    /// is hit by an instruction of the next one: its hits are then returned with the current state.
+    pub fn step<W: Write>(&mut self, output: &mut W) -> io::Result<CycleInfo> {
+        if self.breakpoints.has_unchecked() {
+            self.check_breakpoints(true)?;
+            let hits = self.breakpoints.take_hits();
+            if !hits.is_empty() {
+                return Ok(CycleInfo { hits, ..self.idle_cycle_info() })
+            }
+        }
+
+        let mut cycle_info = CycleInfo {
+            lives_counter: HashMap::with_capacity(self.champions.len()),
+            ..Default::default()
+        };
+
+        let mut record = self.journal.as_ref().map(|_| CycleRecord {
+            last_living_champion: self.last_living_champion,
+            next_pid: self.next_pid,
+            rejected_forks: self.rejected_forks,
+            number_of_lives: self.number_of_lives,
+            cycles_to_die: self.cycles_to_die,
+            cycles: self.cycles,
+            cycle_checks: self.cycle_checks,
+            ..Default::default()
+        });
+
+        self.cycle += 1;
+        self.cycles += 1;
+        let mut deaths = Vec::new();
+        let mut decreased = false;
+        if self.cycles >= self.cycles_to_die {
+            self.cycle_checks += 1;
+            let cycles_to_die = self.cycles_to_die;
+            let last_cycle = self.cycle - 1;
+            let mut dead: Vec<_> = self.processes.iter()
+                .filter(|&(_, p)| last_cycle.saturating_sub(p.context.last_live_cycle) >= cycles_to_die)
+                .map(|(slot, p)| (p.key(), slot))
+                .collect();
+            // processes die in the order they would have executed
+            dead.sort_unstable();
+            for (_, slot) in dead {
+                let process = match self.processes.remove(slot) {
+                    Some(process) => process,
+                    None => continue,
+                };
+                let (pid, champion) = (process.context.pid, process.context.champion);
+                deaths.push((pid, last_cycle.saturating_sub(process.context.last_live_cycle), cycles_to_die));
+                if let Some(count) = self.process_counts.get_mut(&champion) {
+                    *count -= 1;
+                    if *count == 0 {
+                        self.process_counts.remove(&champion);
+                    }
+                }
+                self.notify(Event::ProcessKilled { pid, champion });
+                if let Some(ref mut record) = record {
+                    record.killed.push((slot, process));
+                }
+            }
+            let config = self.config;
+            if self.number_of_lives >= config.nbr_live || self.cycle_checks >= config.max_checks {
+                self.cycles_to_die = self.cycles_to_die.saturating_sub(config.cycle_delta);
+                self.cycle_checks = 0;
+                decreased = true;
+                let cycles_to_die = self.cycles_to_die;
+                self.notify(Event::CyclesToDieDecreased { cycles_to_die });
+            }
+            self.cycles = 0;
+            self.number_of_lives = 0;
+        }
+        let traced = self.trace_cycle(&deaths, decreased);
+
+        cycle_info.cycle = self.cycle;
+        cycle_info.cycles_to_die = self.cycles_to_die;
+
+        let executed = record.as_mut().map(|record| &mut record.executed);
+        traced.and_then(|_| self.execute_processes(&mut cycle_info, executed, output))?;
+
+        if let Some(mut record) = record {
+            record.writes = self.arena.take_writes();
+            if let Some(ref mut journal) = self.journal {
+                journal.push(record);
+            }
+        }
+
+        cycle_info.remaining_processes = self.processes.len();
+        cycle_info.processes_per_champion = self.processes_per_champion();
+        cycle_info.last_living_champion = self.last_living_champion;
+        self.check_breakpoints(false)?;
+        cycle_info.hits = self.breakpoints.take_hits();
+
+        Ok(cycle_info)
+    }
+
+    /// Records the breakpoints hit by the instructions of the next cycle,
+    /// only the ones added since the last check if `unchecked_only`.
+    /// The processes killed before executing them don't hit anything.
+    fn check_breakpoints(&mut self, unchecked_only: bool) -> io::Result<()> {
+        if self.breakpoints.breakpoints().is_empty() {
+            return Ok(())
+        }
+        let (next_cycle, last_cycle) = (self.cycle + 1, self.cycle);
+        let checked = self.cycles + 1 >= self.cycles_to_die;
+        let cycles_to_die = self.cycles_to_die;
+        let mut due: Vec<_> = self.processes.iter()
+            .map(|(_, p)| p)
+            .filter(|p| p.next_cycle == next_cycle)
+            .filter(|p| !checked || last_cycle.saturating_sub(p.context.last_live_cycle) < cycles_to_die)
+            .collect();
+        due.sort_unstable_by_key(|p| p.key());
+
+        for process in due {
+            let ctx = &process.context;
+            let instruction = match self.config.semantics {
+                Semantics::Native => process.instruction,
+                Semantics::Reference => {
+                    let reader = self.arena.read_from(ctx.pc);
+                    match reference::decode(process.op_code, reader, self.config.reg_number)? {
+                        Decoded::Valid { instruction, .. } => Some(instruction),
+                        _ => None,
+                    }
+                },
+            };
+            match instruction {
+                Some(instruction) if unchecked_only => {
+                    self.breakpoints.check_unchecked(ctx.pid, ctx.pc.as_raw(), instruction)
+                },
+                Some(instruction) => self.breakpoints.check(ctx.pid, ctx.pc.as_raw(), instruction),
+                None => (),
+            }
+        }
+        self.breakpoints.mark_checked();
+        Ok(())
+    }
+
+    /// Writes the deaths and cycles lines, the reference VM checks the processes
+    /// at the end of a cycle so they come before the new cycle.
+    fn trace_cycle(&mut self, deaths: &[(ProcessId, usize, usize)], decreased: bool) -> io::Result<()> {
+        if let Some(writer) = self.trace(Verbosity::DEATHS) {
+            for &(pid, cycles, cycles_to_die) in deaths {
+                writeln!(writer, "Process {} hasn't lived for {} cycles (CTD {})", pid.0 + 1, cycles, cycles_to_die)?;
+            }
+        }
+        let (cycle, cycles_to_die) = (self.cycle, self.cycles_to_die);
+        if let Some(writer) = self.trace(Verbosity::CYCLES) {
+            if decreased {
+                writeln!(writer, "Cycle to die is now {}", cycles_to_die)?;
+            }
+            writeln!(writer, "It is now cycle {}", cycle)?;
+        }
+        Ok(())
+    }
+
+    fn execute_processes<W: Write>(&mut self,
+                                   cycle_info: &mut CycleInfo,
+                                   mut executed: Option<&mut Vec<(usize, Process)>>,
+                                   output: &mut W) -> io::Result<()>
+    {
+        while let Some(&Reverse((cycle, key, slot))) = self.queue.peek() {
+            if cycle > self.cycle { break }
+            self.queue.pop();
+            // killed processes leave their next execution in the queue
+            match self.processes.get(slot) {
+                Some(process) if process.key() == key && process.next_cycle == cycle => (),
+                _ => continue,
+            }
+            let mut process = match self.processes.take(slot) {
+                Some(process) => process,
+                None => continue,
+            };
+            if let Some(ref mut executed) = executed {
+                executed.push((slot, process.clone()));
+            }
+
+            let result = self.execute_process(&mut process, output);
+            if let Ok(instr) = result {
+                trace!("execute {:?}", instr);
+                if let Some(Instruction::Live(Direct(argument))) = instr {
+                    let champion_id = live_champion_id(argument);
+                    if self.champions.contains_key(&champion_id) {
+                        let counter = cycle_info.lives_counter.entry(champion_id).or_insert(0);
+                        *counter += 1;
+                        self.notify(Event::LiveReported { pid: process.context.pid, champion: champion_id });
+                    }
+                }
+                process.fetch(&mut self.arena, self.config.semantics, self.cycle);
+                self.queue.push(Reverse((process.next_cycle, key, slot)));
+            }
+            self.processes.put_back(slot, process);
+            result?;
+        }
+        Ok(())
+    }
+
+    /// Executes the instruction the process waited for, returns `None` if it was invalid.
+    fn execute_process<W: Write>(&mut self, process: &mut Process, output: &mut W) -> io::Result<Option<Instruction>> {
+        let ctx = &mut process.context;
+        let pc = ctx.pc.as_raw();
+        let instr = match self.config.semantics {
+            Semantics::Native => process.instruction,
+            Semantics::Reference => {
+                let reader = self.arena.read_from(ctx.pc);
+                match reference::decode(process.op_code, reader, self.config.reg_number)? {
+                    Decoded::Valid { instruction, size } => {
+                        instruction.execute_encoded(size, self, ctx, output)?;
+                        self.notify(Event::InstructionExecuted { pid: ctx.pid, pc, instruction });
+                        return Ok(Some(instruction))
+                    },
+                    Decoded::Invalid { size } => {
+                        Instruction::skip(self, ctx, size)?;
+                        return Ok(None)
+                    },
+                    Decoded::InvalidOpCode => None,
+                }
+            },
+        };
+
+        match instr {
+            Some(instr) => {
+                instr.execute(self, ctx, output)?;
+                self.notify(Event::InstructionExecuted { pid: ctx.pid, pc, instruction: instr });
+            },
+            None => Instruction::execute_noop(ctx),
+        }
+        Ok(instr)
+    }
+
+    /// Executes cycles until the given process completes its current instruction
+    /// or a breakpoint is hit, returns `None` if the process doesn't exist or dies before that.
+    pub fn step_process<W: Write>(&mut self, pid: ProcessId, output: &mut W) -> io::Result<Option<CycleInfo>> {
+        loop {
+            let completes = match self.process(pid) {
+                Some(process) => process.next_cycle == self.cycle + 1,
+                None => return Ok(None),
+            };
+            let cycle_info = self.step(output)?;
+            if completes || !cycle_info.hits.is_empty() {
+                return Ok(self.process(pid).map(|_| cycle_info))
+            }
+        }
+    }
+
+    /// Executes cycles until every process is dead or a limit is reached,
+    /// `on_cycle` is called after each cycle. Breakpoints are ignored.
+    pub fn run<W, F>(&mut self, output: &mut W, mut on_cycle: F) -> io::Result<Termination>
+        where W: Write,
+              F: FnMut(&CycleInfo)
+    {
+        let start = Instant::now();
+        // `step` must not stop before the instructions hitting new breakpoints
+        self.breakpoints.mark_checked();
+        loop {
+            if self.is_over() {
+                return Ok(Termination::AllProcessesDead)
+            }
+            if self.limits.max_cycles.is_some_and(|max| self.cycle >= max) {
+                return Ok(Termination::CycleLimit)
+            }
+            if self.limits.max_duration.is_some_and(|max| start.elapsed() >= max) {
+                return Ok(Termination::TimeLimit)
+            }
+
+            // the skipped cycles are reported as if they had been executed
+            let first_idle = self.cycle + 1;
+            let idle = self.skip_idle_cycles(self.limits.max_cycles.unwrap_or(usize::MAX));
+            if idle != 0 {
+                let mut cycle_info = self.idle_cycle_info();
+                for cycle in first_idle..first_idle + idle {
+                    cycle_info.cycle = cycle;
+                    on_cycle(&cycle_info);
+                }
+            }
+            let cycle_info = self.step(output)?;
+            on_cycle(&cycle_info);
+        }
+    }
+
+    /// Executes cycles until `cycle` has been reached, a breakpoint is hit
+    /// or every process is dead, returns the state of the last executed cycle.
+    pub fn run_until_cycle<W: Write>(&mut self, cycle: usize, output: &mut W) -> io::Result<Option<CycleInfo>> {
+        let mut last_cycle_info = None;
+        while !self.is_over() && self.cycle < cycle {
+            self.skip_idle_cycles(cycle);
+            let cycle_info = self.step(output)?;
+            let hit = !cycle_info.hits.is_empty();
+            last_cycle_info = Some(cycle_info);
+            if hit { break }
+        }
+        Ok(last_cycle_info)
+    }
+
+    /// Advances the counters over the cycles before `cycle` during which
+    /// no process executes and no cycle to die check happens, the cycle
+    /// just before `cycle` is always left to be executed.
+    /// Returns the number of cycles skipped.
+    fn skip_idle_cycles(&mut self, cycle: usize) -> usize {
+        // the journal and the trace need every cycle,
+        // breakpoints are checked the cycle before an execution
+        if self.journal.is_some() || self.trace.is_some() || !self.breakpoints.breakpoints().is_empty() {
+            return 0
+        }
+        let next_execution = self.queue.peek().map_or(usize::MAX, |&Reverse((cycle, _, _))| cycle);
+        let next_check = self.cycle + self.cycles_to_die.saturating_sub(self.cycles).max(1);
+        let idle = next_execution.min(next_check).min(cycle).saturating_sub(self.cycle + 1);
+        self.cycle += idle;
+        self.cycles += idle;
+        idle
+    }
+
+    /// Returns what `step` reports for a cycle during which nothing happens.
+    fn idle_cycle_info(&self) -> CycleInfo {
+        CycleInfo {
+            cycle: self.cycle,
+            remaining_processes: self.processes.len(),
+            processes_per_champion: self.processes_per_champion(),
+            cycles_to_die: self.cycles_to_die,
+            lives_counter: HashMap::new(),
+            last_living_champion: self.last_living_champion,
+            hits: Vec::new(),
+        }
+    }
+
+    /// Executes cycles until the predicate, called after each cycle, returns `true`,
+    /// a breakpoint is hit or every process is dead,
+    /// returns the state of the last executed cycle.
+    pub fn run_until<W, P>(&mut self, output: &mut W, mut predicate: P) -> io::Result<Option<CycleInfo>>
+        where W: Write,
+              P: FnMut(&Machine, &CycleInfo) -> bool
+    {
+        let mut last_cycle_info = None;
+        while !self.is_over() {
+            let cycle_info = self.step(output)?;
+            let stop = predicate(self, &cycle_info);
+            let hit = !cycle_info.hits.is_empty();
+            last_cycle_info = Some(cycle_info);
+            if stop || hit { break }
+        }
+        Ok(last_cycle_info)
+    }
 }
 
 pub struct CycleExecute<'a, W: 'a + Write> {
@@ -92,75 +840,773 @@ pub struct CycleExecute<'a, W: 'a + Write> {
 
 #[derive(Debug, Clone, Default)]
 pub struct CycleInfo {
+    /// The number of cycles executed since the beginning of the match.
+    pub cycle: usize,
     pub remaining_processes: usize,
+    pub processes_per_champion: HashMap<i32, usize>,
     pub cycles_to_die: usize,
     pub lives_counter: HashMap<i32, usize>,
     pub last_living_champion: Option<i32>,
+    /// The watchpoints hit during this cycle and the breakpoints hit
+    /// by the instructions of the next one, which are not executed yet,
+    /// the machine stops at the end of the cycle.
+    pub hits: Vec<Hit>,
 }
 
 impl<'a, W: 'a + Write> Iterator for CycleExecute<'a, W> {
     type Item = io::Result<CycleInfo>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let mut processes = Vec::new();
-        mem::swap(&mut processes, &mut self.machine.processes);
+        match self.machine.step(self.output) {
+            Ok(ref cycle_info) if cycle_info.remaining_processes == 0 => None,
+            result => Some(result),
+        }
+    }
+}
 
-        let mut cycle_info = CycleInfo {
-            lives_counter: HashMap::with_capacity(self.machine.champions.len()),
-            ..Default::default()
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io;
+    use std::sync::{Arc, Mutex};
+    use test_support::{champion, raw_champion};
+    use instruction::parameter::{AltDirect, DirInd, Indirect, IndReg};
+    use breakpoint::{Breakpoint, Watchpoint};
+    use std::time::Duration;
+    use std::mem;
+    use core::{ConfigError, REG_MAX};
+
+    #[test]
+    fn arena_ownership() {
+        let reg = Register::new(1).unwrap();
+        let store = Instruction::Store(reg, IndReg::Indirect(Indirect(5)));
+
+        let mut champions = BTreeMap::new();
+        champions.insert(1, champion(&[store]));
+        champions.insert(2, champion(&[store]));
+
+        let mut machine = Machine::new(champions, VmConfig::with_mem_size(64)).unwrap();
+        let owners = machine.arena.owners().to_vec();
+        assert_eq!(&owners[..5], &[Some(1); 5]);
+        assert_eq!(&owners[5..32], &[None; 27]);
+        assert_eq!(&owners[32..37], &[Some(2); 5]);
+
+        let mut output = io::sink();
+        assert_eq!(machine.cycle_execute(&mut output).take(5).count(), 5);
+
+        let owners = machine.arena.owners();
+        assert_eq!(&owners[5..9], &[Some(1); 4]);
+        assert_eq!(&owners[37..41], &[Some(2); 4]);
+        // r1 holds the negated player number
+        assert_eq!(&machine.arena.as_slice()[5..9], &[0xff, 0xff, 0xff, 0xff]);
+        assert_eq!(&machine.arena.as_slice()[37..41], &[0xff, 0xff, 0xff, 0xfe]);
+    }
+
+    #[test]
+    fn forks_keep_their_lineage() {
+        let mut champions = BTreeMap::new();
+        champions.insert(1, champion(&[Instruction::LongFork(AltDirect(0))]));
+        champions.insert(2, champion(&[Instruction::Fork(AltDirect(0))]));
+
+        let mut machine = Machine::new(champions, VmConfig::with_mem_size(64)).unwrap();
+        let cycle_info = machine.cycle_execute(&mut io::sink()).take(800).last().unwrap().unwrap();
+        assert_eq!(cycle_info.processes_per_champion[&2], 2);
+        assert_eq!(cycle_info.processes_per_champion[&1], 1);
+
+        let fork = machine.process(ProcessId(2)).unwrap();
+        assert_eq!(fork.context.champion, 2);
+        assert_eq!(fork.context.parent, Some(ProcessId(1)));
+        assert!(machine.process(ProcessId(3)).is_none());
+
+        let cycle_info = machine.cycle_execute(&mut io::sink()).take(200).last().unwrap().unwrap();
+        assert_eq!(cycle_info.processes_per_champion[&1], 2);
+        let fork = machine.process(ProcessId(3)).unwrap();
+        assert_eq!(fork.context.champion, 1);
+        assert_eq!(fork.context.parent, Some(ProcessId(0)));
+    }
+
+    #[test]
+    fn observed_events() {
+        let reg = Register::new(1).unwrap();
+        let instrs = [
+            Instruction::Live(Direct(-1)),
+            Instruction::Store(reg, IndReg::Indirect(Indirect(-5))),
+            Instruction::Display(reg),
+        ];
+        let mut champions = BTreeMap::new();
+        champions.insert(1, champion(&instrs));
+
+        let config = VmConfig { cycle_to_die: 30, nbr_live: 1, ..VmConfig::with_mem_size(64) };
+        let mut machine = Machine::new(champions, config).unwrap();
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let recorder = events.clone();
+        machine.add_observer(Box::new(move |event: &Event| recorder.lock().unwrap().push(*event)));
+
+        let mut output = Vec::new();
+        assert_eq!(machine.cycle_execute(&mut output).count(), 30);
+        assert_eq!(output, [0xff]);
+
+        let pid = ProcessId(0);
+        assert_eq!(*events.lock().unwrap(), [
+            Event::InstructionExecuted { pid, pc: 0, instruction: instrs[0] },
+            Event::LiveReported { pid, champion: 1 },
+            Event::MemoryWritten { pid, champion: 1, address: 0, len: 4 },
+            Event::InstructionExecuted { pid, pc: 5, instruction: instrs[1] },
+            Event::Aff { pid, byte: 0xff },
+            Event::InstructionExecuted { pid, pc: 10, instruction: instrs[2] },
+            Event::CyclesToDieDecreased { cycles_to_die: 0 },
+            Event::ProcessKilled { pid, champion: 1 },
+        ]);
+    }
+
+    #[test]
+    fn step_api() {
+        let reg = Register::new(1).unwrap();
+        let instrs = [
+            Instruction::Live(Direct(-1)),
+            Instruction::Store(reg, IndReg::Indirect(Indirect(-5))),
+            Instruction::Display(reg),
+        ];
+        let mut champions = BTreeMap::new();
+        champions.insert(1, champion(&instrs));
+        let mut machine = Machine::new(champions, VmConfig::with_mem_size(64)).unwrap();
+        let mut output = io::sink();
+        let pid = ProcessId(0);
+
+        assert_eq!(machine.step(&mut output).unwrap().cycle, 1);
+        let cycle_info = machine.step_process(pid, &mut output).unwrap().unwrap();
+        assert_eq!(cycle_info.cycle, 10);
+        assert_eq!(cycle_info.lives_counter[&1], 1);
+        assert_eq!(machine.step_process(pid, &mut output).unwrap().unwrap().cycle, 15);
+        assert_eq!(machine.process(pid).unwrap().context.pc.as_raw(), 10);
+
+        assert_eq!(machine.run_until_cycle(100, &mut output).unwrap().unwrap().cycle, 100);
+        assert!(machine.run_until_cycle(100, &mut output).unwrap().is_none());
+
+        let cycle_info = machine.run_until(&mut output, |_, info| info.cycle % 1000 == 0).unwrap().unwrap();
+        assert_eq!(cycle_info.cycle, 1000);
+
+        let cycle_info = machine.run_until(&mut output, |_, _| false).unwrap().unwrap();
+        assert_eq!(cycle_info.remaining_processes, 0);
+        assert!(machine.is_over());
+        assert!(machine.step_process(pid, &mut output).unwrap().is_none());
+    }
+
+    #[test]
+    fn stops_on_breakpoints_and_watchpoints() {
+        let reg = Register::new(1).unwrap();
+        let instrs = [
+            Instruction::Live(Direct(-1)),
+            Instruction::Store(reg, IndReg::Indirect(Indirect(-5))),
+            Instruction::Load(DirInd::Indirect(Indirect(-10)), reg),
+            Instruction::Display(reg),
+        ];
+        let mut champions = BTreeMap::new();
+        champions.insert(1, champion(&instrs));
+        let mut machine = Machine::new(champions, VmConfig::with_mem_size(256)).unwrap();
+        let watchpoint = Watchpoint { address: 3, len: 1, read: true, write: true };
+        machine.breakpoints_mut().add_watchpoint(watchpoint);
+        machine.breakpoints_mut().add_breakpoint(Breakpoint::OpCode(16));
+        let mut output = Vec::new();
+        let pid = ProcessId(0);
+
+        let cycle_info = machine.run_until(&mut output, |_, _| false).unwrap().unwrap();
+        assert_eq!(cycle_info.cycle, 15);
+        assert_eq!(cycle_info.hits, [
+            Hit::Watchpoint { watchpoint, pid, pc: 5, instruction: instrs[1], address: 0, access: Access::Write },
+        ]);
+
+        let cycle_info = machine.run_until(&mut output, |_, _| false).unwrap().unwrap();
+        assert_eq!(cycle_info.cycle, 20);
+        assert_eq!(cycle_info.hits, [
+            Hit::Watchpoint { watchpoint, pid, pc: 10, instruction: instrs[2], address: 0, access: Access::Read },
+        ]);
+
+        // the machine stops before the aff executes and doesn't hit it again when resumed
+        assert!(machine.breakpoints_mut().remove_watchpoint(watchpoint));
+        let cycle_info = machine.step_process(pid, &mut output).unwrap().unwrap();
+        assert_eq!(cycle_info.cycle, 21);
+        assert_eq!(cycle_info.hits, [
+            Hit::Breakpoint { breakpoint: Breakpoint::OpCode(16), pid, pc: 15, instruction: instrs[3] },
+        ]);
+        assert!(output.is_empty());
+        let cycle_info = machine.step_process(pid, &mut output).unwrap().unwrap();
+        assert_eq!(cycle_info.cycle, 22);
+        assert!(cycle_info.hits.is_empty());
+        assert_eq!(output, [0xff]);
+    }
+
+    #[test]
+    fn breakpoints_ignore_processes_about_to_die() {
+        let load = Instruction::Load(DirInd::Direct(Direct(0)), Register::new(2).unwrap());
+        let mut champions = BTreeMap::new();
+        champions.insert(1, champion(&[load, Instruction::ZJump(AltDirect(-7))]));
+        let config = VmConfig { cycle_to_die: 40, ..VmConfig::with_mem_size(256) };
+        let mut machine = Machine::new(champions, config).unwrap();
+        machine.breakpoints_mut().add_breakpoint(Breakpoint::Pc(0));
+
+        // the ld executes every 25 cycles from 5, the process dies at 80 before executing it
+        let mut stops = Vec::new();
+        while let Some(cycle_info) = machine.run_until(&mut io::sink(), |_, _| false).unwrap() {
+            if cycle_info.hits.is_empty() { break }
+            stops.push(cycle_info.cycle);
+        }
+        assert_eq!(stops, [4, 29, 54]);
+        assert!(machine.is_over());
+        assert_eq!(machine.cycle(), 80);
+    }
+
+    #[test]
+    fn breakpoints_added_while_stopped_are_hit() {
+        let load = Instruction::Load(DirInd::Direct(Direct(0)), Register::new(2).unwrap());
+        let mut champions = BTreeMap::new();
+        champions.insert(1, champion(&[load, Instruction::ZJump(AltDirect(-7))]));
+        let mut machine = Machine::new(champions, VmConfig::with_mem_size(256)).unwrap();
+
+        // the ld executes during cycle 5
+        machine.run_until_cycle(4, &mut io::sink()).unwrap();
+        machine.breakpoints_mut().add_breakpoint(Breakpoint::Pc(0));
+        let cycle_info = machine.step(&mut io::sink()).unwrap();
+        assert_eq!(cycle_info.cycle, 4);
+        match cycle_info.hits[..] {
+            [Hit::Breakpoint { breakpoint: Breakpoint::Pc(0), pc: 0, instruction, .. }] => {
+                assert_eq!(instruction, load)
+            },
+            ref other => panic!("unexpected hits {:?}", other),
+        }
+        assert_eq!(machine.cycle(), 4);
+
+        // continuing executes the instruction it stopped before
+        let cycle_info = machine.step(&mut io::sink()).unwrap();
+        assert_eq!(cycle_info.cycle, 5);
+        assert!(cycle_info.hits.is_empty());
+        assert_eq!(machine.processes().next().unwrap().context.pc.as_raw(), 7);
+    }
+
+    struct BrokenOutput;
+
+    impl Write for BrokenOutput {
+        fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+            Err(io::Error::other("broken output"))
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn failed_instructions_stop_being_watched() {
+        let mut champions = BTreeMap::new();
+        champions.insert(1, champion(&[Instruction::Display(Register::new(1).unwrap())]));
+        let mut machine = Machine::new(champions, VmConfig::with_mem_size(256)).unwrap();
+        let watchpoint = Watchpoint { address: 0, len: 256, read: true, write: true };
+        machine.breakpoints_mut().add_watchpoint(watchpoint);
+
+        assert!(machine.run_until(&mut BrokenOutput, |_, _| false).is_err());
+        machine.breakpoints().access(Access::Write, 0, 1, 256);
+        assert!(machine.breakpoints_mut().take_hits().is_empty());
+    }
+
+    #[test]
+    fn snapshot_resumes_identically() {
+        let reg = Register::new(1).unwrap();
+        let mut champions = BTreeMap::new();
+        champions.insert(1, champion(&[
+            Instruction::Live(Direct(-1)),
+            Instruction::Store(reg, IndReg::Indirect(Indirect(20))),
+            Instruction::Fork(AltDirect(-10)),
+        ]));
+        champions.insert(2, champion(&[
+            Instruction::Live(Direct(-2)),
+            Instruction::Display(reg),
+            Instruction::LongFork(AltDirect(-8)),
+        ]));
+        let config = VmConfig { cycle_to_die: 1000, cycle_delta: 200, max_checks: 2, ..VmConfig::with_mem_size(256) };
+
+        let mut machine = Machine::new(champions, config).unwrap();
+        machine.run_until_cycle(900, &mut io::sink()).unwrap();
+        assert!(machine.processes().count() > 2);
+
+        let mut snapshot = Vec::new();
+        machine.write_snapshot(&mut snapshot).unwrap();
+        let mut restored = Machine::read_snapshot(&mut snapshot.as_slice()).unwrap();
+        assert_eq!(restored.cycle(), 900);
+
+        let (mut output, mut restored_output) = (Vec::new(), Vec::new());
+        machine.run_until(&mut output, |_, _| false).unwrap();
+        restored.run_until(&mut restored_output, |_, _| false).unwrap();
+        assert!(!output.is_empty());
+        assert_eq!(output, restored_output);
+        assert_eq!(machine.last_living_champion().map(|(id, _)| id),
+                   restored.last_living_champion().map(|(id, _)| id));
+
+        let (mut end, mut restored_end) = (Vec::new(), Vec::new());
+        machine.write_snapshot(&mut end).unwrap();
+        restored.write_snapshot(&mut restored_end).unwrap();
+        assert!(end == restored_end);
+    }
+
+    #[test]
+    fn snapshot_version_mismatch() {
+        let machine = Machine::new(BTreeMap::new(), VmConfig::default()).unwrap();
+        let mut snapshot = Vec::new();
+        machine.write_snapshot(&mut snapshot).unwrap();
+        snapshot[5] += 1;
+
+        match Machine::read_snapshot(&mut snapshot.as_slice()) {
+            Err(SnapshotError::UnsupportedVersion(6)) => (),
+            Err(other) => panic!("unexpected {:?}", other),
+            Ok(_) => panic!("unexpected success"),
+        }
+    }
+
+    #[test]
+    fn snapshot_sizes_are_bounded() {
+        let mut champions = BTreeMap::new();
+        champions.insert(1, champion(&[Instruction::Live(Direct(-1))]));
+        let config = VmConfig { champ_max_size: usize::MAX, ..VmConfig::with_mem_size(256) };
+        let machine = Machine::new(champions, config).unwrap();
+        let mut snapshot = Vec::new();
+        machine.write_snapshot(&mut snapshot).unwrap();
+        assert!(Machine::read_snapshot(&mut snapshot.as_slice()).is_ok());
+
+        // the mem_size field follows the magic and the version
+        let with_mem_size = |mem_size: usize| {
+            let mut snapshot = snapshot.clone();
+            snapshot[6..14].copy_from_slice(&(mem_size as u64).to_be_bytes());
+            Machine::read_snapshot(&mut snapshot.as_slice())
         };
+        match with_mem_size(usize::MAX / 2) {
+            Err(SnapshotError::Inconsistent(_)) => (),
+            Err(other) => panic!("unexpected {:?}", other),
+            Ok(_) => panic!("unexpected success"),
+        }
+        match with_mem_size(snapshot::MAX_MEM_SIZE) {
+            Err(SnapshotError::Io(ref e)) if e.kind() == io::ErrorKind::UnexpectedEof => (),
+            Err(other) => panic!("unexpected {:?}", other),
+            Ok(_) => panic!("unexpected success"),
+        }
+    }
+
+    #[test]
+    fn rewinds_to_identical_states() {
+        let reg = Register::new(1).unwrap();
+        let mut champions = BTreeMap::new();
+        champions.insert(1, champion(&[
+            Instruction::Live(Direct(-1)),
+            Instruction::Store(reg, IndReg::Indirect(Indirect(20))),
+            Instruction::Fork(AltDirect(-10)),
+        ]));
+        champions.insert(2, champion(&[
+            Instruction::Live(Direct(-2)),
+            Instruction::Display(reg),
+            Instruction::LongFork(AltDirect(-8)),
+        ]));
+        let config = VmConfig { cycle_to_die: 1000, cycle_delta: 200, max_checks: 2, ..VmConfig::with_mem_size(256) };
+        let mut machine = Machine::new(champions, config).unwrap();
+        machine.enable_journal(10_000);
+
+        let mut snapshots = Vec::new();
+        while !machine.is_over() {
+            let mut snapshot = Vec::new();
+            machine.write_snapshot(&mut snapshot).unwrap();
+            snapshots.push(snapshot);
+            machine.step(&mut io::sink()).unwrap();
+        }
+        assert!(machine.cycle() > 4000);
+        assert_eq!(machine.journal_len(), machine.cycle());
+
+        for cycle in [3000, 2999, 1000, 815, 1].iter().cloned() {
+            assert!(machine.rewind_to(cycle));
+            let mut snapshot = Vec::new();
+            machine.write_snapshot(&mut snapshot).unwrap();
+            assert!(snapshot == snapshots[cycle], "cycle {} differs", cycle);
+        }
+
+        assert!(machine.step_back());
+        assert!(!machine.step_back());
+        assert_eq!(machine.cycle(), 0);
+
+        let mut machine = Machine::new(BTreeMap::new(), config).unwrap();
+        machine.enable_journal(2);
+        for _ in 0..5 {
+            machine.step(&mut io::sink()).unwrap();
+        }
+        assert!(!machine.rewind_to(1));
+        assert_eq!(machine.cycle(), 3);
+    }
+
+    #[test]
+    fn explicit_placements() {
+        let live = Instruction::Live(Direct(0));
+        let champions = || {
+            let mut champions = BTreeMap::new();
+            champions.insert(1, champion(&[live, live]));
+            champions.insert(7, champion(&[live]));
+            champions
+        };
+        let config = VmConfig::with_mem_size(64);
+        let placements = |a, b| [(1, a), (7, b)].iter().cloned().collect::<BTreeMap<_, _>>();
+
+        let machine = Machine::with_placements(champions(), &placements(60, 20), config).unwrap();
+        let owners = machine.arena.owners();
+        assert_eq!(&owners[60..], &[Some(1); 4]);
+        assert_eq!(&owners[..6], &[Some(1); 6]);
+        assert_eq!(&owners[20..25], &[Some(7); 5]);
+        let pcs: Vec<_> = machine.processes().map(|p| (p.context.champion, p.context.pc.as_raw())).collect();
+        assert_eq!(pcs, [(7, 20), (1, 60)]);
+
+        assert!(Machine::with_placements(champions(), &placements(10, 0), config).is_ok());
+        assert_eq!(Machine::with_placements(champions(), &placements(10, 19), config).err(),
+                   Some(PlacementError::Overlap { first: 1, second: 7 }));
+        assert_eq!(Machine::with_placements(champions(), &placements(60, 5), config).err(),
+                   Some(PlacementError::Overlap { first: 1, second: 7 }));
+        assert_eq!(Machine::with_placements(champions(), &placements(0, 64), config).err(),
+                   Some(PlacementError::AddressOutOfArena { champion: 7, address: 64 }));
+
+        let mut placements = placements(0, 32);
+        placements.remove(&7);
+        assert_eq!(Machine::with_placements(champions(), &placements, config).err(),
+                   Some(PlacementError::MissingAddress(7)));
+    }
+
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn reference_trace() {
+        let reg = Register::new(1).unwrap();
+        let mut champions = BTreeMap::new();
+        champions.insert(1, champion(&[
+            Instruction::Live(Direct(-1)),
+            Instruction::Store(reg, IndReg::Indirect(Indirect(5))),
+        ]));
+
+        let mut machine = Machine::new(champions, VmConfig::with_mem_size(64)).unwrap();
+        let buffer = SharedBuffer::default();
+        let verbosity = Verbosity::LIVES | Verbosity::OPERATIONS | Verbosity::PC_MOVEMENTS;
+        machine.set_verbosity(verbosity, Box::new(buffer.clone()));
+        machine.run_until_cycle(16, &mut io::sink()).unwrap();
+
+        let trace = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        assert_eq!(trace.lines().collect::<Vec<_>>(), [
+            "P    1 | live -1",
+            "Player 1 (test) is said to be alive",
+            "ADV 5 (0x0000 -> 0x0005) 01 ff ff ff ff ",
+            "P    1 | st r1 5",
+            "ADV 5 (0x0005 -> 0x000a) 03 30 01 00 05 ",
+        ]);
+
+        buffer.0.lock().unwrap().clear();
+        machine.set_verbosity(Verbosity::CYCLES | Verbosity::DEATHS, Box::new(buffer.clone()));
+        machine.run_until(&mut io::sink(), |_, _| false).unwrap();
+        let trace = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        assert!(trace.starts_with("It is now cycle 17\n"));
+        assert!(trace.contains("Process 1 hasn't lived for "));
+    }
+
+    fn champion_process(machine: &Machine, champion: i32) -> &Process {
+        machine.processes().find(|p| p.context.champion == champion).unwrap()
+    }
+
+    #[test]
+    fn reference_zork() {
+        let zork = [0x0b, 0x68, 0x01, 0x00, 0x0f, 0x00, 0x01, 0x06, 0x64, 0x01, 0x00, 0x00,
+                    0x00, 0x00, 0x01, 0x01, 0x00, 0x00, 0x00, 0x01, 0x09, 0xff, 0xfb];
+        let mut champions = BTreeMap::new();
+        champions.insert(1, raw_champion(&zork));
+
+        let config = VmConfig { semantics: Semantics::Reference, ..VmConfig::default() };
+        let mut machine = Machine::new(champions, config).unwrap();
+        machine.run_until_cycle(5000, &mut io::sink()).unwrap();
+        assert!(!machine.is_over());
+        assert_eq!(machine.last_living_champion().map(|(id, _)| id), Some(1));
+        assert_eq!(&machine.arena.as_slice()[15..20], &[0x01, 0xff, 0xff, 0xff, 0xff]);
+    }
+
+    #[test]
+    fn reference_skips_invalid_param_codes() {
+        // ld with a register as first argument
+        let ld = [0x02, 0x50, 0x01, 0x02];
+        for &(semantics, pc) in &[(Semantics::Native, 1), (Semantics::Reference, 4)] {
+            let mut champions = BTreeMap::new();
+            champions.insert(1, raw_champion(&ld));
+            let config = VmConfig { semantics, ..VmConfig::default() };
+            let mut machine = Machine::new(champions, config).unwrap();
+
+            let cycles = if semantics == Semantics::Native { 1 } else { 5 };
+            machine.run_until_cycle(cycles, &mut io::sink()).unwrap();
+            assert_eq!(champion_process(&machine, 1).context.pc.as_raw(), pc, "{:?}", semantics);
+        }
+    }
+
+    #[test]
+    fn reference_latches_the_op_code() {
+        // sti r1, %8, %0 overwrites the opcode and the first argument of
+        // lldi %0, %0, r2 while its process waits for it, r1 holds the negated id
+        let id = -0x01a4_0005;
+        let sti = [0x0b, 0x68, 0x01, 0x00, 0x08, 0x00, 0x00];
+        let lldi = [0x0e, 0xa4, 0x00, 0x00, 0x00, 0x00, 0x02];
+        let reg = Register::new(2).unwrap();
+
+        for &(semantics, value) in &[(Semantics::Native, 0x01a4_0005), (Semantics::Reference, 0x0002_0000)] {
+            let mut champions = BTreeMap::new();
+            champions.insert(id, raw_champion(&sti));
+            champions.insert(2, raw_champion(&lldi));
+            let placements = [(id, 0), (2, 8)].iter().cloned().collect();
+            let config = VmConfig { semantics, ..VmConfig::default() };
+            let mut machine = Machine::with_placements(champions, &placements, config).unwrap();
+
+            machine.run_until_cycle(50, &mut io::sink()).unwrap();
+            let process = champion_process(&machine, 2);
+            assert_eq!(process.context.pc.as_raw(), 15, "{:?}", semantics);
+            assert_eq!(process.context.registers[reg], value, "{:?}", semantics);
+        }
+    }
+
+    #[test]
+    fn reference_lld_carry() {
+        let reg = Register::new(2).unwrap();
+        let lld = Instruction::LongLoad(DirInd::Direct(Direct(5)), reg);
+        for &(semantics, carry) in &[(Semantics::Native, true), (Semantics::Reference, false)] {
+            let mut champions = BTreeMap::new();
+            champions.insert(1, raw_champion(&[0x0d, 0x90, 0x00, 0x00, 0x00, 0x05, 0x02]));
+            let config = VmConfig { semantics, ..VmConfig::default() };
+            let mut machine = Machine::new(champions, config).unwrap();
 
-        self.machine.cycles += 1;
-        if self.machine.cycles >= self.machine.cycles_to_die {
-            self.machine.cycle_checks += 1;
-            processes.retain(|p| p.context.cycle_since_last_live < self.machine.cycles_to_die);
-            if self.machine.number_of_lives >= NBR_LIVE || self.machine.cycle_checks >= MAX_CHECKS {
-                self.machine.cycles_to_die = self.machine.cycles_to_die.saturating_sub(CYCLE_DELTA);
-                self.machine.cycle_checks = 0;
+            machine.run_until_cycle(lld.cycle_cost(), &mut io::sink()).unwrap();
+            let process = champion_process(&machine, 1);
+            assert_eq!(process.context.registers[reg], 5);
+            assert_eq!(process.context.carry, carry, "{:?}", semantics);
+        }
+    }
+
+    #[test]
+    fn process_limit_rejects_forks() {
+        let instrs = [Instruction::Live(Direct(-1)), Instruction::Fork(AltDirect(-5))];
+        let mut champions = BTreeMap::new();
+        champions.insert(1, champion(&instrs));
+        let mut machine = Machine::new(champions, VmConfig::with_mem_size(256)).unwrap();
+        let limits = Limits { max_cycles: Some(5000), max_processes: Some(3), ..Limits::default() };
+        machine.set_limits(limits);
+
+        let rejected = Arc::new(Mutex::new(0));
+        let observed = rejected.clone();
+        machine.add_observer(Box::new(move |event: &Event| {
+            if let Event::ForkRejected { parent, .. } = *event {
+                assert!(parent.is_some());
+                *observed.lock().unwrap() += 1;
             }
-            self.machine.cycles = 0;
-            self.machine.number_of_lives = 0;
+        }));
+
+        let mut max = 0;
+        let termination = machine.run(&mut io::sink(), |info| max = max.max(info.remaining_processes)).unwrap();
+        assert_eq!(termination, Termination::CycleLimit);
+        assert_eq!(machine.cycle(), 5000);
+        assert_eq!(max, 3);
+        assert!(machine.rejected_forks() > 0);
+        assert_eq!(machine.rejected_forks(), *rejected.lock().unwrap());
+    }
+
+    #[test]
+    fn run_stops_at_limits() {
+        let mut champions = BTreeMap::new();
+        champions.insert(1, champion(&[Instruction::Live(Direct(-1)), Instruction::ZJump(AltDirect(-5))]));
+        let mut machine = Machine::new(champions, VmConfig::with_mem_size(256)).unwrap();
+
+        machine.set_limits(Limits { max_duration: Some(Duration::from_secs(0)), ..Limits::default() });
+        assert_eq!(machine.run(&mut io::sink(), |_| ()).unwrap(), Termination::TimeLimit);
+        assert_eq!(machine.cycle(), 0);
+
+        machine.set_limits(Limits { max_cycles: Some(42), ..Limits::default() });
+        let mut cycles = 0;
+        assert_eq!(machine.run(&mut io::sink(), |_| cycles += 1).unwrap(), Termination::CycleLimit);
+        assert_eq!((machine.cycle(), cycles), (42, 42));
+
+        machine.set_limits(Limits::default());
+        assert_eq!(machine.run(&mut io::sink(), |_| ()).unwrap(), Termination::AllProcessesDead);
+        assert!(machine.is_over());
+    }
+
+    #[test]
+    fn champions_larger_than_the_arena() {
+        let live = Instruction::Live(Direct(-1));
+        let mut champions = BTreeMap::new();
+        champions.insert(1, champion(&[live; 3]));
+        champions.insert(2, champion(&[live; 4]));
+
+        match Machine::new(champions, VmConfig::with_mem_size(32)) {
+            Err(PlacementError::TooLarge { size: 35, mem_size: 32 }) => (),
+            Err(other) => panic!("unexpected {:?}", other),
+            Ok(_) => panic!("unexpected success"),
         }
+    }
 
-        cycle_info.cycles_to_die = self.machine.cycles_to_die;
+    #[test]
+    fn invalid_configs_are_rejected() {
+        let fork = champion(&[Instruction::Fork(AltDirect(0))]);
+        let mut champions = BTreeMap::new();
+        champions.insert(1, fork);
 
-        for process in processes.iter_mut().rev() {
-            let ctx = &mut process.context;
-            process.remaining_cycles -= 1;
-            ctx.cycle_since_last_live += 1;
+        // idx_mod is at least 1 in a tiny arena, forks don't divide by zero
+        let mut machine = Machine::new(champions.clone(), VmConfig::with_mem_size(7)).unwrap();
+        machine.set_limits(Limits { max_cycles: Some(1000), ..Limits::default() });
+        assert_eq!(machine.run(&mut io::sink(), |_| ()).unwrap(), Termination::CycleLimit);
+        assert!(machine.process_count() > 1);
 
-            if process.remaining_cycles == 0 {
-                let instr = &mut process.instruction;
-                match *instr {
-                    Some(instr) => if let Err(e) = instr.execute(&mut self.machine, ctx, &mut self.output) {
-                        return Some(Err(e))
-                    },
-                    None => Instruction::execute_noop(ctx)
-                }
-                trace!("execute {:?}", instr);
+        let configs = [
+            (VmConfig { mem_size: 0, ..VmConfig::default() }, ConfigError::EmptyArena),
+            (VmConfig { idx_mod: 0, ..VmConfig::default() }, ConfigError::ZeroIdxMod),
+            (VmConfig { reg_number: 0, ..VmConfig::default() }, ConfigError::InvalidRegNumber(0)),
+            (VmConfig { reg_number: REG_MAX as usize + 1, ..VmConfig::default() },
+             ConfigError::InvalidRegNumber(REG_MAX as usize + 1)),
+        ];
+        for &(config, error) in &configs {
+            assert_eq!(Machine::new(champions.clone(), config).err(), Some(PlacementError::InvalidConfig(error)));
+            let placements = [(1, 0)].iter().cloned().collect();
+            assert_eq!(Machine::with_placements(champions.clone(), &placements, config).err(),
+                       Some(PlacementError::InvalidConfig(error)));
+        }
+    }
 
-                if let Some(Instruction::Live(Direct(champion_id))) = *instr {
-                    if self.machine.champions.contains_key(&champion_id) {
-                        let counter = cycle_info.lives_counter.entry(champion_id).or_insert(0);
-                        *counter += 1;
-                    }
-                }
+    #[test]
+    fn indirect_accesses_wrap_around() {
+        // st r1, -2 then ld -7, r2 both access the last and the first bytes of the arena
+        let reg = Register::new(1).unwrap();
+        let store = Instruction::Store(reg, IndReg::Indirect(Indirect(-2)));
+        let load = Instruction::Load(DirInd::Indirect(Indirect(-7)), Register::new(2).unwrap());
+        let mut champions = BTreeMap::new();
+        champions.insert(1, champion(&[store, load]));
+        let mut machine = Machine::new(champions, VmConfig::with_mem_size(64)).unwrap();
 
-                let reader = self.machine.arena.read_from(ctx.pc);
-                *instr = match Instruction::read_from(reader) {
-                    Ok(instr) => Some(instr),
-                    Err(InstrError::Io(e)) => return Some(Err(e)),
-                    Err(_) => None,
-                };
-                process.remaining_cycles = instr.map(|instr| instr.cycle_cost()).unwrap_or(1);
+        machine.run_until_cycle(store.cycle_cost() + load.cycle_cost(), &mut io::sink()).unwrap();
+        assert_eq!(&machine.arena.as_slice()[62..], &[0xff, 0xff]);
+        assert_eq!(&machine.arena.as_slice()[..2], &[0xff, 0xff]);
+        assert_eq!(machine.arena.owner(machine.arena.index(63)), Some(1));
+        let process = machine.processes().next().unwrap();
+        assert_eq!(process.context.pc.as_raw(), 10);
+        assert_eq!(process.context.registers[Register::new(2).unwrap()], -1);
+    }
+
+    #[test]
+    fn self_modifying_code_is_decoded_again() {
+        // the st overwrites the live with `zjmp %-10` before jumping back to it
+        let (r2, r3) = (Register::new(2).unwrap(), Register::new(3).unwrap());
+        let instrs = [
+            Instruction::Load(DirInd::Direct(Direct(0x09ff_f600)), r2),
+            Instruction::Live(Direct(-1)),
+            Instruction::Store(r2, IndReg::Indirect(Indirect(-5))),
+            Instruction::Load(DirInd::Direct(Direct(0)), r3),
+            Instruction::ZJump(AltDirect(-17)),
+        ];
+        let mut champions = BTreeMap::new();
+        champions.insert(1, champion(&instrs));
+        let mut machine = Machine::new(champions, VmConfig::with_mem_size(256)).unwrap();
+        let executed = Arc::new(Mutex::new(Vec::new()));
+        let recorder = executed.clone();
+        machine.add_observer(Box::new(move |event: &Event| {
+            if let Event::InstructionExecuted { pc, instruction, .. } = *event {
+                recorder.lock().unwrap().push((pc, instruction));
+            }
+        }));
+
+        while executed.lock().unwrap().len() < 6 {
+            machine.step(&mut io::sink()).unwrap();
+        }
+        let executed = executed.lock().unwrap();
+        assert_eq!(executed[1], (7, instrs[1]));
+        assert_eq!(executed[4], (24, instrs[4]));
+        assert_eq!(executed[5], (7, Instruction::ZJump(AltDirect(-10))));
+    }
+
+    #[test]
+    fn skipping_idle_cycles_is_identical() {
+        let load = Instruction::Load(DirInd::Direct(Direct(0)), Register::new(2).unwrap());
+        let mut champions = BTreeMap::new();
+        champions.insert(1, champion(&[Instruction::Live(Direct(-1)), Instruction::LongFork(AltDirect(-5))]));
+        champions.insert(2, champion(&[Instruction::Fork(AltDirect(12)), load]));
+        let config = VmConfig { cycle_to_die: 300, ..VmConfig::with_mem_size(512) };
+        let mut stepped = Machine::new(champions.clone(), config).unwrap();
+        let mut skipped = Machine::new(champions, config).unwrap();
+
+        for &cycle in &[1, 999, 1000, 1001, 2500, 4000, 10_000] {
+            while stepped.cycle() < cycle && !stepped.is_over() {
+                stepped.step(&mut io::sink()).unwrap();
             }
+            skipped.run_until_cycle(cycle, &mut io::sink()).unwrap();
+
+            let (mut expected, mut snapshot) = (Vec::new(), Vec::new());
+            stepped.write_snapshot(&mut expected).unwrap();
+            skipped.write_snapshot(&mut snapshot).unwrap();
+            assert!(expected == snapshot, "cycle {}", cycle);
+        }
+        assert!(skipped.is_over());
+    }
+
+    #[test]
+    fn run_skips_idle_cycles_like_stepping() {
+        // the first champion lives every 810 cycles until the cycle to die gets shorter,
+        // the second one dies without living
+        let load = Instruction::Load(DirInd::Direct(Direct(0)), Register::new(2).unwrap());
+        let mut champions = BTreeMap::new();
+        champions.insert(1, champion(&[Instruction::Live(Direct(-1)), Instruction::Fork(AltDirect(-5))]));
+        champions.insert(2, champion(&[Instruction::Fork(AltDirect(12)), load, Instruction::ZJump(AltDirect(-7))]));
+        let config = VmConfig { cycle_delta: 100, nbr_live: 8, ..VmConfig::with_mem_size(1024) };
+        let limits = Limits { max_cycles: Some(30_000), max_processes: Some(64), ..Limits::default() };
+
+        // the cycle, what it reports and the events it sent
+        type State = (usize, usize, Vec<(i32, usize)>, usize, Vec<(i32, usize)>, Option<i32>, Vec<Event>);
+        let state = |cycle_info: &CycleInfo, events: &Arc<Mutex<Vec<Event>>>| -> State {
+            let mut processes: Vec<_> = cycle_info.processes_per_champion.iter().map(|(&id, &n)| (id, n)).collect();
+            let mut lives: Vec<_> = cycle_info.lives_counter.iter().map(|(&id, &n)| (id, n)).collect();
+            processes.sort_unstable();
+            lives.sort_unstable();
+            let events = mem::take(&mut *events.lock().unwrap());
+            (cycle_info.cycle, cycle_info.remaining_processes, processes, cycle_info.cycles_to_die,
+             lives, cycle_info.last_living_champion, events)
+        };
+        let machine = || {
+            let mut machine = Machine::new(champions.clone(), config).unwrap();
+            machine.set_limits(limits);
+            let events = Arc::new(Mutex::new(Vec::new()));
+            let observed = events.clone();
+            machine.add_observer(Box::new(move |event: &Event| observed.lock().unwrap().push(*event)));
+            (machine, events)
+        };
+
+        let (mut stepped, events) = machine();
+        let mut expected = Vec::new();
+        while !stepped.is_over() && stepped.cycle() < 30_000 {
+            let cycle_info = stepped.step(&mut io::sink()).unwrap();
+            expected.push(state(&cycle_info, &events));
+        }
+
+        let (mut skipped, events) = machine();
+        let mut states = Vec::new();
+        let termination = skipped.run(&mut io::sink(), |cycle_info| states.push(state(cycle_info, &events))).unwrap();
+        assert_eq!(termination, Termination::AllProcessesDead);
+        assert_eq!(states.len(), expected.len());
+        for (state, expected) in states.iter().zip(&expected) {
+            assert_eq!(state, expected);
         }
-        self.machine.processes.append(&mut processes);
-        cycle_info.remaining_processes = self.machine.processes.len();
-        cycle_info.last_living_champion = self.machine.last_living_champion;
 
-        if !self.machine.processes.is_empty() {
-            Some(Ok(cycle_info))
-        } else { None }
+        let (mut expected, mut snapshot) = (Vec::new(), Vec::new());
+        stepped.write_snapshot(&mut expected).unwrap();
+        skipped.write_snapshot(&mut snapshot).unwrap();
+        assert!(expected == snapshot);
+        // both champions died along the way
+        let deaths = states.iter().flat_map(|state| &state.6);
+        assert!(deaths.filter(|event| matches!(event, Event::ProcessKilled { .. })).count() > 2);
     }
 }
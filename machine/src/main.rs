@@ -1,32 +1,245 @@
 extern crate env_logger;
 extern crate machine;
 
+use std::collections::BTreeMap;
 use std::env::args;
 use std::fs::File;
 use std::net::TcpStream;
-use std::{io, process};
-use machine::Machine;
-use machine::champion::Champion;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::{io, process, error};
+use machine::{Limits, Machine, MatchResult, OutcomeRecorder, PlacementStrategy, Termination, Verbosity};
+use machine::champion::{Champion, LoadOptions};
+use machine::core::{Semantics, VmConfig};
 
-fn failable_main() -> io::Result<()> {
+const USAGE: &str = "usage: machine [-dump cycle | -d cycle] [-v level] [--reference] [--json] [--no-talk] \
+                     [--placement even|random|random-order] [--seed seed] \
+                     [--max-cycles cycles] [--max-processes count] [--time-limit seconds] \
+                     [[-n number] [-a address] champion.cor] ...";
+
+/// The number of processes is sampled in the outcome every `SAMPLE_INTERVAL` cycles.
+const SAMPLE_INTERVAL: usize = 1000;
+
+/// A champion file given on the command line,
+/// with the number and load address chosen by the `-n` and `-a` flags.
+#[derive(Debug, PartialEq, Eq)]
+struct ChampionArg {
+    path: String,
+    number: Option<i32>,
+    address: Option<usize>,
+}
+
+fn parse_address(value: &str) -> Option<usize> {
+    match value.strip_prefix("0x") {
+        Some(hex) => usize::from_str_radix(hex, 16).ok(),
+        None => value.parse().ok(),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Placement {
+    Even,
+    Random,
+    RandomOrder,
+}
+
+/// Stops after `cycle` and prints the arena, `bytes_per_line` bytes per line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Dump {
+    cycle: usize,
+    bytes_per_line: usize,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct Options {
+    champions: Vec<ChampionArg>,
+    placement: Placement,
+    seed: Option<u64>,
+    dump: Option<Dump>,
+    verbosity: Verbosity,
+    semantics: Semantics,
+    json: bool,
+    /// Sends the bytes displayed by `aff` to the talk server.
+    talk: bool,
+    limits: Limits,
+}
+
+fn parse_args<I: Iterator<Item = String>>(mut args: I) -> Result<Options, String> {
+    let mut champions = Vec::new();
+    let (mut number, mut address) = (None, None);
+    let (mut placement, mut seed, mut dump) = (Placement::Even, None, None);
+    let mut verbosity = Verbosity::ESSENTIALS;
+    let mut semantics = Semantics::Native;
+    let mut json = false;
+    let mut talk = true;
+    let mut limits = Limits::default();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--placement" => {
+                placement = match args.next().as_deref() {
+                    Some("even") => Placement::Even,
+                    Some("random") => Placement::Random,
+                    Some("random-order") => Placement::RandomOrder,
+                    _ => return Err("--placement expects even, random or random-order".to_string()),
+                };
+            },
+            "--seed" => {
+                let value = args.next().ok_or("--seed expects a number")?;
+                seed = Some(value.parse().map_err(|_| format!("invalid seed {:?}", value))?);
+            },
+            "-dump" | "-d" => {
+                let value = args.next().ok_or_else(|| format!("{} expects a cycle", arg))?;
+                let cycle = value.parse().map_err(|_| format!("invalid dump cycle {:?}", value))?;
+                let bytes_per_line = if arg == "-d" { 64 } else { 32 };
+                dump = Some(Dump { cycle, bytes_per_line });
+            },
+            "--max-cycles" => {
+                let value = args.next().ok_or("--max-cycles expects a number of cycles")?;
+                limits.max_cycles = Some(value.parse().map_err(|_| format!("invalid cycle limit {:?}", value))?);
+            },
+            "--max-processes" => {
+                let value = args.next().ok_or("--max-processes expects a number of processes")?;
+                limits.max_processes = Some(value.parse().map_err(|_| format!("invalid process limit {:?}", value))?);
+            },
+            "--time-limit" => {
+                let value = args.next().ok_or("--time-limit expects a number of seconds")?;
+                let seconds = value.parse().ok().and_then(|seconds| Duration::try_from_secs_f64(seconds).ok());
+                limits.max_duration = Some(seconds.ok_or_else(|| format!("invalid time limit {:?}", value))?);
+            },
+            "--reference" => semantics = Semantics::Reference,
+            "--json" => json = true,
+            "--no-talk" => talk = false,
+            "-v" => {
+                let value = args.next().ok_or("-v expects a verbosity level")?;
+                let bits = value.parse().map_err(|_| format!("invalid verbosity level {:?}", value))?;
+                verbosity = Verbosity::from_bits(bits);
+            },
+            "-n" => {
+                let value = args.next().ok_or("-n expects a champion number")?;
+                let value = value.parse().ok().filter(|&number| number > 0)
+                    .ok_or_else(|| format!("invalid champion number {:?}", value))?;
+                number = Some(value);
+            },
+            "-a" => {
+                let value = args.next().ok_or("-a expects a load address")?;
+                let value = parse_address(&value).ok_or_else(|| format!("invalid load address {:?}", value))?;
+                address = Some(value);
+            },
+            flag if flag.starts_with('-') => return Err(format!("unknown flag {}\n{}", flag, USAGE)),
+            _ => champions.push(ChampionArg { path: arg, number: number.take(), address: address.take() }),
+        }
+    }
+
+    if number.is_some() || address.is_some() {
+        return Err(format!("-n and -a must be followed by a champion\n{}", USAGE))
+    }
+    Ok(Options { champions, placement, seed, dump, verbosity, semantics, json, talk, limits })
+}
+
+/// Gives the champions without a `-n` flag the lowest numbers not already taken,
+/// players are numbered from 1 like in the reference VM.
+fn champion_numbers(champions: &[ChampionArg]) -> Result<Vec<i32>, String> {
+    let mut taken = Vec::new();
+    for number in champions.iter().filter_map(|c| c.number) {
+        if taken.contains(&number) {
+            return Err(format!("champion number {} is used twice", number))
+        }
+        taken.push(number);
+    }
+
+    let mut next = 1;
+    Ok(champions.iter().map(|champion| {
+        champion.number.unwrap_or_else(|| {
+            while taken.contains(&next) { next += 1 }
+            taken.push(next);
+            next
+        })
+    }).collect())
+}
+
+fn failable_main() -> Result<(), Box<dyn error::Error>> {
     let _ = env_logger::init();
+    let options = parse_args(args().skip(1))?;
+    let config = VmConfig { semantics: options.semantics, ..VmConfig::default() };
+    let champion_args = options.champions;
+    let numbers = champion_numbers(&champion_args)?;
+
+    let load_options = LoadOptions { max_size: config.champ_max_size, ..Default::default() };
+    let mut champions = BTreeMap::new();
+    for (arg, &number) in champion_args.iter().zip(&numbers) {
+        let mut file = File::open(&arg.path)?;
+        if !options.json {
+            println!("reading file at {}", arg.path);
+        }
+        champions.insert(number, Champion::load(&mut file, load_options)?);
+    }
+
+    let seed = options.seed.unwrap_or_else(|| {
+        SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos() as u64).unwrap_or(0)
+    });
+    let strategy = match options.placement {
+        Placement::Even => PlacementStrategy::Even,
+        Placement::Random => PlacementStrategy::Random { seed },
+        Placement::RandomOrder => PlacementStrategy::RandomOrder { seed },
+    };
+    if let (Some(seed), false) = (strategy.seed(), options.json) {
+        println!("placement seed {}", seed);
+    }
+
+    let talk_stream = if options.talk { TcpStream::connect("127.0.0.1:14315").ok() } else { None };
+    let mut talk_stream = talk_stream.map(|x| Box::new(x) as Box<dyn io::Write>)
+                                     .unwrap_or_else(|| Box::new(io::sink()));
+
+    let mut machine = if champion_args.iter().any(|arg| arg.address.is_some()) {
+        // champions without -a keep the address chosen by the strategy
+        let mut placements = strategy.placements(&champions, config.mem_size)?;
+        for (arg, number) in champion_args.iter().zip(numbers) {
+            if let Some(address) = arg.address {
+                placements.insert(number, address % config.mem_size);
+            }
+        }
+        Machine::with_placements(champions, &placements, config)?
+    } else {
+        Machine::with_strategy(champions, strategy, config)?
+    };
+
+    if options.verbosity != Verbosity::ESSENTIALS {
+        machine.set_verbosity(options.verbosity, Box::new(io::stdout()));
+    }
+
+    // the dump cycle is handled as a cycle limit
+    let mut limits = options.limits;
+    if let Some(dump) = options.dump {
+        limits.max_cycles = Some(limits.max_cycles.map_or(dump.cycle, |max| max.min(dump.cycle)));
+    }
+    machine.set_limits(limits);
 
-    let enum_args = args().skip(1).enumerate();
-    let champions: Result<_, io::Error> = enum_args.map(|(id, path)| {
-            let mut file = File::open(&path)?;
-            println!("reading file at {}", path);
-            Ok((id as i32, Champion::new(&mut file)?))
-        }).collect();
+    let mut recorder = OutcomeRecorder::new(&machine, SAMPLE_INTERVAL);
+    let termination = machine.run(&mut talk_stream, |cycle_info| recorder.record(cycle_info))?;
 
-    let mut talk_stream = TcpStream::connect("127.0.0.1:14315")
-                                .map(|x| Box::new(x) as Box<io::Write>)
-                                .unwrap_or_else(|_| Box::new(io::sink()));
+    if let (Some(dump), Termination::CycleLimit) = (options.dump, termination) {
+        if machine.cycle() == dump.cycle {
+            print!("{}", machine.arena.dump(dump.bytes_per_line));
+            return Ok(())
+        }
+    }
 
-    let mut machine = Machine::new(champions?);
-    let _cycle_info = machine.cycle_execute(&mut talk_stream).last();
-    match machine.last_living_champion() {
-        Some((id, champ)) => println!("A winner is {}({}), {}", id, champ.name, champ.comment),
-        None => println!("Sadly, no winner has been found"),
+    let mut outcome = recorder.finish(&machine, termination);
+    // champions placed with -a are not recorded with the strategy by the machine
+    outcome.placement_seed = strategy.seed();
+    if options.json {
+        outcome.write_json(&mut io::stdout())?;
+        return Ok(())
+    }
+    match termination {
+        Termination::CycleLimit => println!("Cycle limit reached at cycle {}", machine.cycle()),
+        Termination::TimeLimit => println!("Time limit reached at cycle {}", machine.cycle()),
+        Termination::AllProcessesDead | Termination::Stopped => (),
+    }
+    match (outcome.result, machine.last_living_champion()) {
+        (MatchResult::Winner(_), Some((id, champ))) => println!("A winner is {}({}), {}", id, champ.name, champ.comment),
+        (MatchResult::Draw(ids), _) => println!("Draw between {:?}", ids),
+        _ => println!("Sadly, no winner has been found"),
     }
 
     Ok(())
@@ -38,3 +251,99 @@ fn main() {
         process::exit(1);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> impl Iterator<Item = String> + '_ {
+        line.split_whitespace().map(ToString::to_string)
+    }
+
+    #[test]
+    fn numbers_and_addresses() {
+        let champions = parse_args(args("a.cor -n 3 -a 0x100 b.cor -a 12 c.cor")).unwrap().champions;
+        assert_eq!(champions, [
+            ChampionArg { path: "a.cor".to_string(), number: None, address: None },
+            ChampionArg { path: "b.cor".to_string(), number: Some(3), address: Some(256) },
+            ChampionArg { path: "c.cor".to_string(), number: None, address: Some(12) },
+        ]);
+
+        let champions = parse_args(args("a.cor b.cor")).unwrap().champions;
+        assert_eq!(champion_numbers(&champions).unwrap(), [1, 2]);
+        let champions = parse_args(args("-n 1 a.cor b.cor -n 5 c.cor d.cor")).unwrap().champions;
+        assert_eq!(champion_numbers(&champions).unwrap(), [1, 2, 5, 3]);
+        assert!(parse_args(args("-n 0 a.cor")).is_err());
+        assert!(parse_args(args("-n -2 a.cor")).is_err());
+    }
+
+    #[test]
+    fn placement_and_seed() {
+        let options = parse_args(args("--placement random-order --seed 42 a.cor")).unwrap();
+        assert_eq!(options.placement, Placement::RandomOrder);
+        assert_eq!(options.seed, Some(42));
+        assert_eq!(parse_args(args("a.cor")).unwrap().placement, Placement::Even);
+    }
+
+    #[test]
+    fn dump() {
+        let options = parse_args(args("-dump 1500 a.cor")).unwrap();
+        assert_eq!(options.dump, Some(Dump { cycle: 1500, bytes_per_line: 32 }));
+        let options = parse_args(args("-d 0 a.cor")).unwrap();
+        assert_eq!(options.dump, Some(Dump { cycle: 0, bytes_per_line: 64 }));
+        assert_eq!(parse_args(args("a.cor")).unwrap().dump, None);
+    }
+
+    #[test]
+    fn verbosity() {
+        let options = parse_args(args("-v 6 a.cor")).unwrap();
+        assert!(options.verbosity.contains(Verbosity::CYCLES | Verbosity::OPERATIONS));
+        assert!(!options.verbosity.contains(Verbosity::LIVES));
+        assert!(parse_args(args("-v all a.cor")).is_err());
+    }
+
+    #[test]
+    fn json() {
+        assert!(parse_args(args("--json a.cor")).unwrap().json);
+        assert!(!parse_args(args("a.cor")).unwrap().json);
+    }
+
+    #[test]
+    fn talk() {
+        assert!(parse_args(args("a.cor")).unwrap().talk);
+        assert!(!parse_args(args("--no-talk a.cor")).unwrap().talk);
+    }
+
+    #[test]
+    fn semantics() {
+        assert_eq!(parse_args(args("a.cor")).unwrap().semantics, Semantics::Native);
+        assert_eq!(parse_args(args("--reference a.cor")).unwrap().semantics, Semantics::Reference);
+    }
+
+    #[test]
+    fn limits() {
+        let options = parse_args(args("--max-cycles 100 --max-processes 8 --time-limit 1.5 a.cor")).unwrap();
+        assert_eq!(options.limits, Limits {
+            max_cycles: Some(100),
+            max_processes: Some(8),
+            max_duration: Some(Duration::from_millis(1500)),
+        });
+        assert_eq!(parse_args(args("a.cor")).unwrap().limits, Limits::default());
+    }
+
+    #[test]
+    fn invalid_args() {
+        assert!(parse_args(args("a.cor -n")).is_err());
+        assert!(parse_args(args("-a zork a.cor")).is_err());
+        assert!(parse_args(args("a.cor -n 2")).is_err());
+        assert!(parse_args(args("-x a.cor")).is_err());
+        assert!(parse_args(args("--placement evenly a.cor")).is_err());
+        assert!(parse_args(args("--seed -1 a.cor")).is_err());
+        assert!(parse_args(args("-dump a.cor")).is_err());
+        assert!(parse_args(args("--max-processes many a.cor")).is_err());
+        assert!(parse_args(args("--time-limit -1 a.cor")).is_err());
+
+        let champions = parse_args(args("-n 1 a.cor -n 1 b.cor")).unwrap().champions;
+        assert!(champion_numbers(&champions).is_err());
+    }
+}
@@ -0,0 +1,30 @@
+use instruction::Instruction;
+use process::ProcessId;
+
+/// Something that happened in the machine, addresses are arena offsets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    /// The instruction at `pc` has been executed by this process.
+    InstructionExecuted { pid: ProcessId, pc: usize, instruction: Instruction },
+    /// `len` bytes have been written from `address`, wrapping around the arena.
+    MemoryWritten { pid: ProcessId, champion: i32, address: usize, len: usize },
+    ProcessSpawned { pid: ProcessId, parent: Option<ProcessId>, champion: i32, pc: usize },
+    /// The process wasn't spawned because the process limit was reached.
+    ForkRejected { pid: ProcessId, parent: Option<ProcessId>, champion: i32 },
+    /// The process didn't report a live during the last cycle to die period.
+    ProcessKilled { pid: ProcessId, champion: i32 },
+    /// A process reported a live for an existing champion.
+    LiveReported { pid: ProcessId, champion: i32 },
+    CyclesToDieDecreased { cycles_to_die: usize },
+    Aff { pid: ProcessId, byte: u8 },
+}
+
+pub trait MachineObserver {
+    fn notify(&mut self, event: &Event);
+}
+
+impl<F: FnMut(&Event)> MachineObserver for F {
+    fn notify(&mut self, event: &Event) {
+        self(event)
+    }
+}
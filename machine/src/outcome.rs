@@ -0,0 +1,286 @@
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use machine::{Machine, CycleInfo};
+
+/// Who won the match.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MatchResult {
+    /// The last champion reported alive.
+    Winner(i32),
+    /// The match was stopped while these champions still had processes.
+    Draw(Vec<i32>),
+    /// No process ever reported a live for an existing champion.
+    NoLive,
+}
+
+/// Why the match ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Termination {
+    AllProcessesDead,
+    /// The match was interrupted before every process died.
+    Stopped,
+    CycleLimit,
+    TimeLimit,
+}
+
+impl Termination {
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            Termination::AllProcessesDead => "all_processes_dead",
+            Termination::Stopped => "stopped",
+            Termination::CycleLimit => "cycle_limit",
+            Termination::TimeLimit => "time_limit",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChampionOutcome {
+    pub id: i32,
+    pub name: String,
+    /// The number of lives reported for this champion during the whole match.
+    pub lives: usize,
+    pub last_live_cycle: Option<usize>,
+    /// The number of processes still alive at the end of the match.
+    pub processes: usize,
+}
+
+/// The number of processes alive at a given cycle.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProcessSample {
+    pub cycle: usize,
+    pub processes: BTreeMap<i32, usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MatchOutcome {
+    pub result: MatchResult,
+    pub termination: Termination,
+    pub cycle: usize,
+    pub cycles_to_die: usize,
+    pub champions: Vec<ChampionOutcome>,
+    pub process_counts: Vec<ProcessSample>,
+    /// The seed the champions were placed with, if they were placed randomly.
+    pub placement_seed: Option<u64>,
+    /// The number of forks rejected because of the process limit.
+    pub rejected_forks: usize,
+}
+
+/// Gathers the live counts and process samples of a match, cycle after cycle.
+#[derive(Debug)]
+pub struct OutcomeRecorder {
+    sample_interval: usize,
+    lives: BTreeMap<i32, (usize, Option<usize>)>,
+    samples: Vec<ProcessSample>,
+}
+
+impl OutcomeRecorder {
+    /// Samples the number of processes every `sample_interval` cycles,
+    /// at the start and at the end of the match.
+    pub fn new(machine: &Machine, sample_interval: usize) -> Self {
+        let sample = ProcessSample {
+            cycle: machine.cycle(),
+            processes: machine.processes_per_champion().into_iter().collect(),
+        };
+        OutcomeRecorder { sample_interval, lives: BTreeMap::new(), samples: vec![sample] }
+    }
+
+    pub fn record(&mut self, cycle_info: &CycleInfo) {
+        for (&id, &count) in &cycle_info.lives_counter {
+            let lives = self.lives.entry(id).or_insert((0, None));
+            lives.0 += count;
+            lives.1 = Some(cycle_info.cycle);
+        }
+        if self.sample_interval != 0 && cycle_info.cycle.is_multiple_of(self.sample_interval) {
+            self.samples.push(ProcessSample {
+                cycle: cycle_info.cycle,
+                processes: cycle_info.processes_per_champion.iter().map(|(&id, &count)| (id, count)).collect(),
+            });
+        }
+    }
+
+    pub fn finish(mut self, machine: &Machine, termination: Termination) -> MatchOutcome {
+        let processes: BTreeMap<_, _> = machine.processes_per_champion().into_iter().collect();
+        if self.samples.last().map(|sample| sample.cycle) != Some(machine.cycle()) {
+            self.samples.push(ProcessSample { cycle: machine.cycle(), processes: processes.clone() });
+        }
+
+        let champions = machine.champions().iter().map(|(&id, champion)| {
+            let (lives, last_live_cycle) = self.lives.get(&id).cloned().unwrap_or((0, None));
+            ChampionOutcome {
+                id,
+                name: champion.name.clone(),
+                lives,
+                last_live_cycle,
+                processes: processes.get(&id).cloned().unwrap_or(0),
+            }
+        }).collect();
+
+        let alive: Vec<_> = processes.iter().filter(|&(_, &count)| count != 0).map(|(&id, _)| id).collect();
+        let result = match machine.last_living_champion() {
+            _ if termination != Termination::AllProcessesDead && alive.len() > 1 => MatchResult::Draw(alive),
+            Some((id, _)) => MatchResult::Winner(id),
+            None => MatchResult::NoLive,
+        };
+
+        MatchOutcome {
+            result,
+            termination,
+            cycle: machine.cycle(),
+            cycles_to_die: machine.cycles_to_die(),
+            champions,
+            process_counts: self.samples,
+            placement_seed: machine.placement_strategy().and_then(|strategy| strategy.seed()),
+            rejected_forks: machine.rejected_forks(),
+        }
+    }
+}
+
+impl MatchOutcome {
+    pub fn write_json<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        let (result, winner) = match self.result {
+            MatchResult::Winner(id) => ("winner", Some(id)),
+            MatchResult::Draw(_) => ("draw", None),
+            MatchResult::NoLive => ("no_live", None),
+        };
+        write!(writer, "{{\"result\":\"{}\",\"winner\":", result)?;
+        write_option(writer, winner)?;
+        write!(writer, ",\"draw\":[")?;
+        if let MatchResult::Draw(ref ids) = self.result {
+            write_list(writer, ids, |writer, id| write!(writer, "{}", id))?;
+        }
+        write!(writer, "],\"termination\":\"{}\",\"cycle\":{},\"cycles_to_die\":{},\"placement_seed\":",
+               self.termination.as_str(), self.cycle, self.cycles_to_die)?;
+        write_option(writer, self.placement_seed)?;
+        write!(writer, ",\"rejected_forks\":{}", self.rejected_forks)?;
+
+        write!(writer, ",\"champions\":[")?;
+        write_list(writer, &self.champions, |writer, champion| {
+            write!(writer, "{{\"id\":{},\"name\":", champion.id)?;
+            write_string(writer, &champion.name)?;
+            write!(writer, ",\"lives\":{},\"last_live_cycle\":", champion.lives)?;
+            write_option(writer, champion.last_live_cycle)?;
+            write!(writer, ",\"processes\":{}}}", champion.processes)
+        })?;
+
+        write!(writer, "],\"process_counts\":[")?;
+        write_list(writer, &self.process_counts, |writer, sample| {
+            write!(writer, "{{\"cycle\":{},\"processes\":{{", sample.cycle)?;
+            let counts: Vec<_> = sample.processes.iter().collect();
+            write_list(writer, &counts, |writer, &(id, count)| write!(writer, "\"{}\":{}", id, count))?;
+            write!(writer, "}}}}")
+        })?;
+        writeln!(writer, "]}}")
+    }
+}
+
+fn write_list<W, T, F>(writer: &mut W, items: &[T], mut write_item: F) -> io::Result<()>
+    where W: Write,
+          F: FnMut(&mut W, &T) -> io::Result<()>
+{
+    for (i, item) in items.iter().enumerate() {
+        if i != 0 { writer.write_all(b",")? }
+        write_item(writer, item)?;
+    }
+    Ok(())
+}
+
+fn write_option<W: Write, T: ToString>(writer: &mut W, value: Option<T>) -> io::Result<()> {
+    match value {
+        Some(value) => writer.write_all(value.to_string().as_bytes()),
+        None => writer.write_all(b"null"),
+    }
+}
+
+fn write_string<W: Write>(writer: &mut W, s: &str) -> io::Result<()> {
+    writer.write_all(b"\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => writer.write_all(b"\\\"")?,
+            '\\' => writer.write_all(b"\\\\")?,
+            '\n' => writer.write_all(b"\\n")?,
+            '\r' => writer.write_all(b"\\r")?,
+            '\t' => writer.write_all(b"\\t")?,
+            c if (c as u32) < 0x20 => write!(writer, "\\u{:04x}", c as u32)?,
+            c => write!(writer, "{}", c)?,
+        }
+    }
+    writer.write_all(b"\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use instruction::Instruction;
+    use instruction::parameter::{AltDirect, Direct, DirInd, Register};
+    use placement::PlacementStrategy;
+    use core::VmConfig;
+    use test_support::named_champion as champion;
+
+    fn run(machine: &mut Machine, cycles: usize, recorder: &mut OutcomeRecorder) {
+        for cycle_info in machine.cycle_execute(&mut io::sink()).take(cycles) {
+            recorder.record(&cycle_info.unwrap());
+        }
+    }
+
+    #[test]
+    fn winner_and_lives() {
+        // both champions end in an endless zjmp loop
+        let reg = Register::new(16).unwrap();
+        let load = Instruction::Load(DirInd::Direct(Direct(0)), reg);
+        let mut champions = BTreeMap::new();
+        champions.insert(1, champion("liver", &[Instruction::Live(Direct(-1)), load, Instruction::ZJump(AltDirect(0))]));
+        champions.insert(2, champion("\"quiet\"", &[load, Instruction::ZJump(AltDirect(0))]));
+
+        let config = VmConfig { cycle_to_die: 100, ..VmConfig::with_mem_size(256) };
+        let strategy = PlacementStrategy::RandomOrder { seed: 7 };
+        let mut machine = Machine::with_strategy(champions, strategy, config).unwrap();
+        let mut recorder = OutcomeRecorder::new(&machine, 50);
+        run(&mut machine, 1000, &mut recorder);
+        assert!(machine.is_over());
+
+        let outcome = recorder.finish(&machine, Termination::AllProcessesDead);
+        assert_eq!(outcome.result, MatchResult::Winner(1));
+        assert_eq!(outcome.placement_seed, Some(7));
+        assert_eq!(outcome.champions[0], ChampionOutcome {
+            id: 1,
+            name: "liver".to_string(),
+            lives: 1,
+            last_live_cycle: Some(10),
+            processes: 0,
+        });
+        assert_eq!(outcome.champions[1].lives, 0);
+        assert_eq!(outcome.process_counts[0].cycle, 0);
+        assert_eq!(outcome.process_counts[1].cycle, 50);
+        assert_eq!(outcome.process_counts.last().unwrap().cycle, outcome.cycle);
+
+        let mut json = Vec::new();
+        outcome.write_json(&mut json).unwrap();
+        let json = String::from_utf8(json).unwrap();
+        assert!(json.starts_with("{\"result\":\"winner\",\"winner\":1,\"draw\":[],\"termination\":\"all_processes_dead\""));
+        assert!(json.contains("\"placement_seed\":7,"));
+        assert!(json.contains("{\"id\":2,\"name\":\"\\\"quiet\\\"\",\"lives\":0,\"last_live_cycle\":null,\"processes\":0}"));
+        assert!(json.contains("{\"cycle\":0,\"processes\":{\"1\":1,\"2\":1}}"));
+    }
+
+    #[test]
+    fn draw_and_no_live() {
+        let mut champions = BTreeMap::new();
+        champions.insert(1, champion("a", &[Instruction::ZJump(AltDirect(0))]));
+        champions.insert(2, champion("b", &[Instruction::ZJump(AltDirect(0))]));
+        let mut machine = Machine::new(champions, VmConfig::with_mem_size(256)).unwrap();
+
+        let mut recorder = OutcomeRecorder::new(&machine, 0);
+        run(&mut machine, 10, &mut recorder);
+        let outcome = recorder.finish(&machine, Termination::Stopped);
+        assert_eq!(outcome.result, MatchResult::Draw(vec![1, 2]));
+        assert_eq!(outcome.placement_seed, None);
+        assert_eq!(outcome.process_counts.len(), 2);
+
+        let mut recorder = OutcomeRecorder::new(&machine, 0);
+        run(&mut machine, 10_000, &mut recorder);
+        let outcome = recorder.finish(&machine, Termination::AllProcessesDead);
+        assert_eq!(outcome.result, MatchResult::NoLive);
+    }
+}
@@ -0,0 +1,201 @@
+use std::collections::BTreeMap;
+use std::{error, fmt};
+use champion::Champion;
+use core::ConfigError;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PlacementError {
+    MissingAddress(i32),
+    AddressOutOfArena { champion: i32, address: usize },
+    Overlap { first: i32, second: i32 },
+    /// The programs are larger than the arena.
+    TooLarge { size: usize, mem_size: usize },
+    InvalidConfig(ConfigError),
+}
+
+impl From<ConfigError> for PlacementError {
+    fn from(error: ConfigError) -> PlacementError {
+        PlacementError::InvalidConfig(error)
+    }
+}
+
+impl error::Error for PlacementError {}
+
+impl fmt::Display for PlacementError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PlacementError::MissingAddress(id) => write!(f, "champion {} has no load address", id),
+            PlacementError::AddressOutOfArena { champion, address } => {
+                write!(f, "champion {} load address {} is outside of the arena", champion, address)
+            },
+            PlacementError::Overlap { first, second } => {
+                write!(f, "champions {} and {} overlap in the arena", first, second)
+            },
+            PlacementError::TooLarge { size, mem_size } => {
+                write!(f, "champions need {} bytes but the arena is only {} bytes long", size, mem_size)
+            },
+            PlacementError::InvalidConfig(ref error) => write!(f, "invalid config: {}", error),
+        }
+    }
+}
+
+/// How champions are laid out in the arena when no address is given.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PlacementStrategy {
+    /// Evenly spaced, in the order of the champion ids.
+    #[default]
+    Even,
+    /// Random non overlapping addresses, in a random order.
+    Random { seed: u64 },
+    /// Evenly spaced, in a random order.
+    RandomOrder { seed: u64 },
+}
+
+impl PlacementStrategy {
+    pub fn seed(&self) -> Option<u64> {
+        match *self {
+            PlacementStrategy::Even => None,
+            PlacementStrategy::Random { seed } | PlacementStrategy::RandomOrder { seed } => Some(seed),
+        }
+    }
+
+    /// Returns the load address of each champion, the same seed always gives the same addresses.
+    pub fn placements(&self, champions: &BTreeMap<i32, Champion>, mem_size: usize)
+        -> Result<BTreeMap<i32, usize>, PlacementError>
+    {
+        let mut ids: Vec<_> = champions.keys().cloned().collect();
+        let step = mem_size.checked_div(ids.len()).unwrap_or(0);
+
+        match *self {
+            PlacementStrategy::Even => (),
+            PlacementStrategy::RandomOrder { seed } => Rng::new(seed).shuffle(&mut ids),
+            PlacementStrategy::Random { seed } => {
+                let size: usize = champions.values().map(|c| c.program.as_slice().len()).sum();
+                let free = mem_size.checked_sub(size).ok_or(PlacementError::TooLarge { size, mem_size })?;
+
+                // the free bytes are randomly split between the champions,
+                // then everything is rotated by a random offset
+                let mut rng = Rng::new(seed);
+                rng.shuffle(&mut ids);
+                let mut gaps: Vec<_> = ids.iter().map(|_| rng.below(free + 1)).collect();
+                gaps.sort_unstable();
+                let offset = rng.below(mem_size.max(1));
+
+                let mut used = 0;
+                return Ok(ids.iter().zip(gaps).map(|(id, gap)| {
+                    let address = (offset + used + gap) % mem_size;
+                    used += champions[id].program.as_slice().len();
+                    (*id, address)
+                }).collect())
+            },
+        }
+
+        Ok(ids.into_iter().enumerate().map(|(i, id)| (id, i * step)).collect())
+    }
+}
+
+/// Checks that the programs fit in the arena, that every champion
+/// has an address in the arena and that programs don't overlap.
+pub fn check_placements(champions: &BTreeMap<i32, Champion>,
+                        placements: &BTreeMap<i32, usize>,
+                        mem_size: usize) -> Result<(), PlacementError>
+{
+    let size: usize = champions.values().map(|c| c.program.as_slice().len()).sum();
+    if size > mem_size {
+        return Err(PlacementError::TooLarge { size, mem_size })
+    }
+
+    let mut ranges = Vec::with_capacity(champions.len());
+    for (id, champion) in champions {
+        let address = *placements.get(id).ok_or(PlacementError::MissingAddress(*id))?;
+        if address >= mem_size {
+            return Err(PlacementError::AddressOutOfArena { champion: *id, address })
+        }
+        ranges.push((*id, address, champion.program.as_slice().len()));
+    }
+
+    for (i, &(first, a_start, a_len)) in ranges.iter().enumerate() {
+        for &(second, b_start, b_len) in &ranges[i + 1..] {
+            let b_after_a = (b_start + mem_size - a_start) % mem_size;
+            let a_after_b = (a_start + mem_size - b_start) % mem_size;
+            if (b_len != 0 && b_after_a < a_len) || (a_len != 0 && a_after_b < b_len) {
+                return Err(PlacementError::Overlap { first, second })
+            }
+        }
+    }
+    Ok(())
+}
+
+/// A xorshift64* generator, good enough to shuffle champions
+/// and stable across platforms and releases.
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        // splitmix64 to spread poor seeds like 0 or 1
+        let mut z = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        Rng((z ^ (z >> 31)) | 1)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    /// Returns a number in `0..n`, `n` must not be zero.
+    fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+
+    fn shuffle<T>(&mut self, slice: &mut [T]) {
+        for i in (1..slice.len()).rev() {
+            let j = self.below(i + 1);
+            slice.swap(i, j);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_support::raw_champion;
+
+    fn champions(sizes: &[usize]) -> BTreeMap<i32, Champion> {
+        sizes.iter().enumerate().map(|(id, &size)| (id as i32, raw_champion(&vec![1; size]))).collect()
+    }
+
+    #[test]
+    fn even() {
+        let placements = PlacementStrategy::Even.placements(&champions(&[10, 10, 10]), 99).unwrap();
+        assert_eq!(placements.into_iter().collect::<Vec<_>>(), [(0, 0), (1, 33), (2, 66)]);
+    }
+
+    #[test]
+    fn random_order_keeps_even_addresses() {
+        let champions = champions(&[10, 10, 10, 10]);
+        let mut addresses: Vec<_> = PlacementStrategy::RandomOrder { seed: 42 }
+                                        .placements(&champions, 100).unwrap()
+                                        .values().cloned().collect();
+        addresses.sort_unstable();
+        assert_eq!(addresses, [0, 25, 50, 75]);
+    }
+
+    #[test]
+    fn random_is_reproducible_and_never_overlaps() {
+        let champions = champions(&[300, 682, 1, 682]);
+        for seed in 0..200 {
+            let strategy = PlacementStrategy::Random { seed };
+            let placements = strategy.placements(&champions, 4096).unwrap();
+            assert_eq!(placements, strategy.placements(&champions, 4096).unwrap());
+            check_placements(&champions, &placements, 4096).unwrap();
+        }
+
+        let strategy = PlacementStrategy::Random { seed: 0 };
+        assert_ne!(strategy.placements(&champions, 4096), PlacementStrategy::Random { seed: 1 }.placements(&champions, 4096));
+        assert_eq!(strategy.placements(&champions, 1000), Err(PlacementError::TooLarge { size: 1665, mem_size: 1000 }));
+    }
+}
@@ -1,44 +1,124 @@
+use std::io::{self, Read, Write};
 use std::ops::{Index, IndexMut};
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
 use instruction::parameter::Register;
 use arena::ArenaIndex;
-use core::REG_NUMBER;
+use process::ProcessId;
+use snapshot::{SnapshotError, write_usize, read_usize, write_bool, read_bool};
+use core::REG_MAX;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Context {
+    pub pid: ProcessId,
+    /// The process that forked this one, `None` for the initial processes.
+    pub parent: Option<ProcessId>,
+    /// The id of the champion this process has been created for.
+    pub champion: i32,
     pub pc: ArenaIndex,
     pub carry: bool,
-    pub cycle_since_last_live: usize,
+    /// The cycle of the last live reported by this process, or of its creation.
+    pub last_live_cycle: usize,
     pub registers: Registers,
 }
 
 impl Context {
-    pub fn new(pc: ArenaIndex) -> Self {
+    pub fn new(pid: ProcessId, champion: i32, pc: ArenaIndex, reg_number: usize) -> Self {
         Context {
-            pc: pc,
+            pid,
+            parent: None,
+            champion,
+            pc,
             carry: false,
-            cycle_since_last_live: 0,
-            registers: Registers::new(),
+            last_live_cycle: 0,
+            registers: Registers::new(reg_number),
         }
     }
 
-    pub fn clean_fork(&self) -> Context {
+    /// Returns the context of a child process with the given id created at `cycle`,
+    /// it belongs to the same champion.
+    pub fn clean_fork(&self, pid: ProcessId, cycle: usize) -> Context {
         Context {
+            pid,
+            parent: Some(self.pid),
+            champion: self.champion,
             pc: self.pc,
             carry: self.carry,
-            cycle_since_last_live: 0,
-            registers: self.registers.clone(),
+            last_live_cycle: cycle,
+            registers: self.registers,
+        }
+    }
+
+    pub fn write_snapshot<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        write_usize(writer, self.pid.0)?;
+        write_bool(writer, self.parent.is_some())?;
+        write_usize(writer, self.parent.map_or(0, |pid| pid.0))?;
+        writer.write_i32::<BigEndian>(self.champion)?;
+        write_usize(writer, self.pc.as_raw())?;
+        write_bool(writer, self.carry)?;
+        write_usize(writer, self.last_live_cycle)?;
+        writer.write_u8(self.registers.len() as u8)?;
+        for value in self.registers.as_slice() {
+            writer.write_i32::<BigEndian>(*value)?;
+        }
+        Ok(())
+    }
+
+    pub fn read_snapshot<R: Read>(reader: &mut R, mem_size: usize) -> Result<Self, SnapshotError> {
+        let pid = ProcessId(read_usize(reader)?);
+        let has_parent = read_bool(reader)?;
+        let parent = ProcessId(read_usize(reader)?);
+        let champion = reader.read_i32::<BigEndian>()?;
+        let pc = read_usize(reader)?;
+        if pc >= mem_size {
+            return Err(SnapshotError::Inconsistent("process pc is outside of the arena"))
+        }
+        let carry = read_bool(reader)?;
+        let last_live_cycle = read_usize(reader)?;
+
+        let reg_number = reader.read_u8()?;
+        if reg_number == 0 || reg_number > REG_MAX {
+            return Err(SnapshotError::Inconsistent("invalid number of registers"))
         }
+        let mut registers = Registers::new(reg_number as usize);
+        for value in &mut registers.inner[..reg_number as usize] {
+            *value = reader.read_i32::<BigEndian>()?;
+        }
+
+        Ok(Context {
+            pid,
+            parent: if has_parent { Some(parent) } else { None },
+            champion,
+            pc: ArenaIndex::from_raw(pc, mem_size),
+            carry,
+            last_live_cycle,
+            registers,
+        })
     }
 }
 
-#[derive(Debug, Clone)]
+/// The registers are stored inline, `REG_MAX` at most.
+#[derive(Debug, Clone, Copy)]
 pub struct Registers {
-    inner: [i32; REG_NUMBER]
+    inner: [i32; REG_MAX as usize],
+    len: u8,
 }
 
 impl Registers {
-    pub fn new() -> Self {
-        Registers { inner: [0; REG_NUMBER] }
+    /// Creates `reg_number` registers, up to `REG_MAX`.
+    pub fn new(reg_number: usize) -> Self {
+        Registers { inner: [0; REG_MAX as usize], len: reg_number.min(REG_MAX as usize) as u8 }
+    }
+
+    pub fn as_slice(&self) -> &[i32] {
+        &self.inner[..self.len as usize]
+    }
+
+    pub fn len(&self) -> usize {
+        self.len as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
     }
 }
 
@@ -1,30 +1,103 @@
 mod context;
 
-pub use self::context::{Context, Registers};
-use instruction::Instruction;
-use instruction::Error as InstrError;
+pub use self::context::Context;
+use std::io::{self, Read, Write};
+use std::fmt;
+use std::cmp::Reverse;
+use byteorder::{ReadBytesExt, WriteBytesExt};
+use instruction::{reference, Instruction};
 use arena::Arena;
+use snapshot::{SnapshotError, write_usize, read_usize, write_bool, read_bool};
+use core::Semantics;
 
-#[derive(Debug)]
+/// A process id, unique for the whole match and never reused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ProcessId(pub usize);
+
+impl fmt::Display for ProcessId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// Processes execute by ascending key during a cycle: the oldest first
+/// and, among the ones created during the same cycle, the latest forks first.
+pub type ProcessKey = (usize, Reverse<ProcessId>);
+
+#[derive(Debug, Clone)]
 pub struct Process {
     pub context: Context,
-    pub remaining_cycles: usize,
+    /// The cycle during which the process has been created.
+    pub birth_cycle: usize,
+    /// The cycle during which the process will execute its instruction.
+    pub next_cycle: usize,
+    /// The instruction decoded when the process started waiting,
+    /// always `None` with the reference semantics.
     pub instruction: Option<Instruction>,
+    /// The byte at _PC_ when the process started waiting.
+    pub op_code: u8,
 }
 
 // FIXME: Add logging here !
 impl Process {
-    pub fn new(context: Context, arena: &Arena) -> Self {
-        let mut reader = arena.read_from(context.pc);
-        let maybe_instr = match Instruction::read_from(&mut reader) {
-            Ok(instruction) => Some(instruction),
-            Err(InstrError::Io(error)) => panic!("{}", error),
-            Err(_) => None,
+    /// Creates a process during `cycle`.
+    pub fn new(context: Context, arena: &mut Arena, semantics: Semantics, cycle: usize) -> Self {
+        let mut process = Process { context, birth_cycle: cycle, next_cycle: cycle, instruction: None, op_code: 0 };
+        process.fetch(arena, semantics, cycle);
+        process
+    }
+
+    /// Reads what the process will execute once the cost of the instruction
+    /// has been waited from `cycle`, the reference semantics only latch the opcode.
+    pub fn fetch(&mut self, arena: &mut Arena, semantics: Semantics, cycle: usize) {
+        self.op_code = arena.byte(self.context.pc);
+        let cost = match semantics {
+            Semantics::Native => {
+                self.instruction = read_instruction(&self.context, arena);
+                self.instruction.map(|instr| instr.cycle_cost()).unwrap_or(1)
+            },
+            Semantics::Reference => {
+                self.instruction = None;
+                reference::cycle_cost(self.op_code).unwrap_or(1)
+            },
         };
-        Process {
-            context: context,
-            remaining_cycles: maybe_instr.map(|instr| instr.cycle_cost()).unwrap_or(1),
-            instruction: maybe_instr,
+        self.next_cycle = cycle + cost;
+    }
+
+    pub fn key(&self) -> ProcessKey {
+        (self.birth_cycle, Reverse(self.context.pid))
+    }
+
+    pub fn write_snapshot<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        self.context.write_snapshot(writer)?;
+        write_usize(writer, self.birth_cycle)?;
+        write_usize(writer, self.next_cycle)?;
+        writer.write_u8(self.op_code)?;
+        write_bool(writer, self.instruction.is_some())?;
+        match self.instruction {
+            Some(instr) => instr.write_to(writer),
+            None => Ok(()),
         }
     }
+
+    /// Reads a process, the pending instruction is restored as it was decoded
+    /// even if the arena has been modified since.
+    pub fn read_snapshot<R: Read>(reader: &mut R, mem_size: usize) -> Result<Self, SnapshotError> {
+        let context = Context::read_snapshot(reader, mem_size)?;
+        let birth_cycle = read_usize(reader)?;
+        let next_cycle = read_usize(reader)?;
+        let op_code = reader.read_u8()?;
+        let instruction = if read_bool(reader)? {
+            Some(Instruction::read_from(&mut *reader)?)
+        } else {
+            None
+        };
+        Ok(Process { context, birth_cycle, next_cycle, instruction, op_code })
+    }
+}
+
+/// Decodes the instruction at the context's _PC_, an instruction using
+/// a register the context doesn't have is considered invalid.
+pub fn read_instruction(context: &Context, arena: &mut Arena) -> Option<Instruction> {
+    arena.decode(context.pc).filter(|instr| instr.highest_register() as usize <= context.registers.len())
 }
@@ -1,24 +1,6 @@
-use std::io::{self, Read};
-use std::fmt;
-use std::error::Error;
+use std::io::Read;
 use core::CHAMP_MAX_SIZE;
-
-#[derive(Debug)]
-pub struct InvalidProgramSize {
-    size: usize,
-}
-
-impl Error for InvalidProgramSize {
-    fn description(&self) -> &str {
-        "program size is too long"
-    }
-}
-
-impl fmt::Display for InvalidProgramSize {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}, {} exceeds {}", self.description(), self.size, CHAMP_MAX_SIZE)
-    }
-}
+use champion::LoadError;
 
 #[derive(Debug, Clone)]
 pub struct Program {
@@ -26,13 +8,20 @@ pub struct Program {
 }
 
 impl Program {
-    pub fn new<R: Read>(size: usize, reader: &mut R) -> io::Result<Self> {
-        if size > CHAMP_MAX_SIZE {
-            return Err(io::Error::new(io::ErrorKind::InvalidData, InvalidProgramSize { size }))
+    pub fn new<R: Read>(size: usize, reader: &mut R) -> Result<Self, LoadError> {
+        Program::with_max_size(size, CHAMP_MAX_SIZE, reader)
+    }
+
+    pub fn with_max_size<R: Read>(size: usize, max_size: usize, reader: &mut R) -> Result<Self, LoadError> {
+        if size > max_size {
+            return Err(LoadError::ProgramTooLarge { size, max_size })
         }
 
-        let mut program = vec![0; size];
-        reader.read_exact(&mut program)?;
+        let mut program = Vec::with_capacity(size);
+        reader.take(size as u64).read_to_end(&mut program)?;
+        if program.len() != size {
+            return Err(LoadError::TruncatedProgram { prog_size: size, actual_size: program.len() })
+        }
 
         Ok(Program { inner: program })
     }
@@ -0,0 +1,153 @@
+use std::mem;
+use process::Process;
+
+/// Stores the processes in slots reused once their process is removed,
+/// a slot index stays valid as long as its process is alive.
+#[derive(Debug, Default)]
+pub struct ProcessSlab {
+    slots: Vec<Option<Process>>,
+    free: Vec<usize>,
+    len: usize,
+}
+
+impl ProcessSlab {
+    pub fn new() -> Self {
+        ProcessSlab::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the slot of the process, the last freed slot if there is one.
+    pub fn insert(&mut self, process: Process) -> usize {
+        self.len += 1;
+        match self.free.pop() {
+            Some(slot) => {
+                self.slots[slot] = Some(process);
+                slot
+            },
+            None => {
+                self.slots.push(Some(process));
+                self.slots.len() - 1
+            },
+        }
+    }
+
+    pub fn remove(&mut self, slot: usize) -> Option<Process> {
+        let process = self.slots.get_mut(slot).and_then(Option::take);
+        if process.is_some() {
+            self.len -= 1;
+            self.free.push(slot);
+        }
+        process
+    }
+
+    /// Puts a removed process back in its former slot,
+    /// returns it if the slot has been reused since.
+    pub fn restore(&mut self, slot: usize, process: Process) -> Option<Process> {
+        // undone removals are usually the last freed slots
+        match self.free.iter().rposition(|&free| free == slot) {
+            Some(index) => {
+                self.free.remove(index);
+                self.slots[slot] = Some(process);
+                self.len += 1;
+                None
+            },
+            None => Some(process),
+        }
+    }
+
+    pub fn get(&self, slot: usize) -> Option<&Process> {
+        self.slots.get(slot).and_then(Option::as_ref)
+    }
+
+    /// Replaces the process in this slot, returns the previous one.
+    pub fn replace(&mut self, slot: usize, process: Process) -> Option<Process> {
+        match self.slots.get_mut(slot) {
+            Some(&mut Some(ref mut old)) => Some(mem::replace(old, process)),
+            _ => None,
+        }
+    }
+
+    /// Takes the process out of its slot, the slot is not freed
+    /// and the process must be put back with `put_back`.
+    pub fn take(&mut self, slot: usize) -> Option<Process> {
+        self.slots.get_mut(slot).and_then(Option::take)
+    }
+
+    pub fn put_back(&mut self, slot: usize, process: Process) {
+        debug_assert!(self.slots[slot].is_none());
+        self.slots[slot] = Some(process);
+    }
+
+    /// Returns the processes with their slot, in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &Process)> {
+        self.slots.iter().enumerate().filter_map(|(slot, p)| p.as_ref().map(|p| (slot, p)))
+    }
+
+    /// Removes the processes for which the predicate returns `false`.
+    pub fn retain<F: FnMut(&Process) -> bool>(&mut self, mut predicate: F) {
+        for slot in 0..self.slots.len() {
+            if self.slots[slot].as_ref().is_some_and(|p| !predicate(p)) {
+                self.remove(slot);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arena::Arena;
+    use process::{Context, ProcessId};
+    use core::Semantics;
+
+    fn process(pid: usize) -> Process {
+        let mut arena = Arena::new(64);
+        let context = Context::new(ProcessId(pid), -1, arena.index(0), 16);
+        Process::new(context, &mut arena, Semantics::Native, 0)
+    }
+
+    #[test]
+    fn reuses_freed_slots() {
+        let mut slab = ProcessSlab::new();
+        let slots: Vec<_> = (0..4).map(|pid| slab.insert(process(pid))).collect();
+        assert_eq!(slots, [0, 1, 2, 3]);
+
+        assert_eq!(slab.remove(1).map(|p| p.context.pid), Some(ProcessId(1)));
+        assert!(slab.remove(1).is_none());
+        slab.retain(|p| p.context.pid != ProcessId(3));
+        assert_eq!(slab.len(), 2);
+
+        assert_eq!(slab.insert(process(4)), 3);
+        assert_eq!(slab.insert(process(5)), 1);
+        assert_eq!(slab.insert(process(6)), 4);
+        let mut pids: Vec<_> = slab.iter().map(|(slot, p)| (slot, p.context.pid.0)).collect();
+        pids.sort();
+        assert_eq!(pids, [(0, 0), (1, 5), (2, 2), (3, 4), (4, 6)]);
+
+        let removed = slab.remove(2).unwrap();
+        slab.remove(0);
+        assert!(slab.restore(2, removed).is_none());
+        assert!(slab.restore(1, process(7)).is_some());
+        assert_eq!(slab.insert(process(2)), 0);
+        assert_eq!(slab.len(), 5);
+    }
+
+    #[test]
+    fn taken_slots_are_not_reused() {
+        let mut slab = ProcessSlab::new();
+        slab.insert(process(0));
+        let taken = slab.take(0).unwrap();
+        assert_eq!(slab.len(), 1);
+        assert!(slab.get(0).is_none());
+        assert_eq!(slab.insert(process(1)), 1);
+        slab.put_back(0, taken);
+        assert_eq!(slab.get(0).map(|p| p.context.pid), Some(ProcessId(0)));
+    }
+}
@@ -0,0 +1,139 @@
+use std::io::{self, Read, Write};
+use std::{error, fmt};
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use instruction::Error as InstrError;
+use champion::LoadError;
+use placement::PlacementStrategy;
+use core::Semantics;
+
+/// The first bytes of every snapshot.
+pub const SNAPSHOT_MAGIC: [u8; 4] = *b"CWSN";
+
+/// Bumped each time the layout of a snapshot changes,
+/// snapshots written by another version are rejected.
+pub const SNAPSHOT_VERSION: u16 = 5;
+
+/// The largest arena a snapshot may describe, it is checked
+/// before anything is allocated from a corrupt size.
+pub const MAX_MEM_SIZE: usize = 1 << 24;
+
+#[derive(Debug)]
+pub enum SnapshotError {
+    Io(io::Error),
+    InvalidMagic([u8; 4]),
+    UnsupportedVersion(u16),
+    Champion(LoadError),
+    InvalidInstruction(InstrError),
+    /// The snapshot is well formed but describes an impossible machine.
+    Inconsistent(&'static str),
+}
+
+impl From<io::Error> for SnapshotError {
+    fn from(error: io::Error) -> Self {
+        SnapshotError::Io(error)
+    }
+}
+
+impl From<LoadError> for SnapshotError {
+    fn from(error: LoadError) -> Self {
+        match error {
+            LoadError::Io(e) => SnapshotError::Io(e),
+            error => SnapshotError::Champion(error),
+        }
+    }
+}
+
+impl From<InstrError> for SnapshotError {
+    fn from(error: InstrError) -> Self {
+        match error {
+            InstrError::Io(e) => SnapshotError::Io(e),
+            error => SnapshotError::InvalidInstruction(error),
+        }
+    }
+}
+
+impl error::Error for SnapshotError {}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SnapshotError::Io(ref e) => e.fmt(f),
+            SnapshotError::InvalidMagic(magic) => write!(f, "invalid snapshot magic {:?}", magic),
+            SnapshotError::UnsupportedVersion(version) => {
+                write!(f, "unsupported snapshot version {}, expected {}", version, SNAPSHOT_VERSION)
+            },
+            SnapshotError::Champion(ref e) => write!(f, "invalid champion: {}", e),
+            SnapshotError::InvalidInstruction(ref e) => write!(f, "invalid pending instruction: {:?}", e),
+            SnapshotError::Inconsistent(reason) => write!(f, "inconsistent snapshot: {}", reason),
+        }
+    }
+}
+
+pub fn write_usize<W: Write>(writer: &mut W, value: usize) -> io::Result<()> {
+    writer.write_u64::<BigEndian>(value as u64)
+}
+
+pub fn read_usize<R: Read>(reader: &mut R) -> io::Result<usize> {
+    reader.read_u64::<BigEndian>().map(|value| value as usize)
+}
+
+pub fn write_bool<W: Write>(writer: &mut W, value: bool) -> io::Result<()> {
+    writer.write_u8(value as u8)
+}
+
+pub fn read_bool<R: Read>(reader: &mut R) -> Result<bool, SnapshotError> {
+    match reader.read_u8()? {
+        0 => Ok(false),
+        1 => Ok(true),
+        _ => Err(SnapshotError::Inconsistent("invalid boolean")),
+    }
+}
+
+pub fn write_option_i32<W: Write>(writer: &mut W, value: Option<i32>) -> io::Result<()> {
+    write_bool(writer, value.is_some())?;
+    writer.write_i32::<BigEndian>(value.unwrap_or(0))
+}
+
+pub fn read_option_i32<R: Read>(reader: &mut R) -> Result<Option<i32>, SnapshotError> {
+    let is_some = read_bool(reader)?;
+    let value = reader.read_i32::<BigEndian>()?;
+    Ok(if is_some { Some(value) } else { None })
+}
+
+pub fn write_semantics<W: Write>(writer: &mut W, semantics: Semantics) -> io::Result<()> {
+    writer.write_u8(match semantics {
+        Semantics::Native => 0,
+        Semantics::Reference => 1,
+    })
+}
+
+pub fn read_semantics<R: Read>(reader: &mut R) -> Result<Semantics, SnapshotError> {
+    match reader.read_u8()? {
+        0 => Ok(Semantics::Native),
+        1 => Ok(Semantics::Reference),
+        _ => Err(SnapshotError::Inconsistent("invalid semantics")),
+    }
+}
+
+pub fn write_placement<W: Write>(writer: &mut W, placement: Option<PlacementStrategy>) -> io::Result<()> {
+    let (tag, seed) = match placement {
+        None => (0, 0),
+        Some(PlacementStrategy::Even) => (1, 0),
+        Some(PlacementStrategy::Random { seed }) => (2, seed),
+        Some(PlacementStrategy::RandomOrder { seed }) => (3, seed),
+    };
+    writer.write_u8(tag)?;
+    writer.write_u64::<BigEndian>(seed)
+}
+
+pub fn read_placement<R: Read>(reader: &mut R) -> Result<Option<PlacementStrategy>, SnapshotError> {
+    let tag = reader.read_u8()?;
+    let seed = reader.read_u64::<BigEndian>()?;
+    match tag {
+        0 => Ok(None),
+        1 => Ok(Some(PlacementStrategy::Even)),
+        2 => Ok(Some(PlacementStrategy::Random { seed })),
+        3 => Ok(Some(PlacementStrategy::RandomOrder { seed })),
+        _ => Err(SnapshotError::Inconsistent("invalid placement strategy")),
+    }
+}
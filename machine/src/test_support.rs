@@ -0,0 +1,37 @@
+//! Champions built from instructions, for the tests and the benchmarks.
+
+use champion::Champion;
+use instruction::Instruction;
+use program::Program;
+
+/// Encodes the instructions one after the other.
+pub fn assemble(instrs: &[Instruction]) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    for instr in instrs {
+        instr.write_to(&mut bytes).unwrap();
+    }
+    bytes
+}
+
+/// A champion named `test` running the instructions.
+pub fn champion(instrs: &[Instruction]) -> Champion {
+    named_champion("test", instrs)
+}
+
+pub fn named_champion(name: &str, instrs: &[Instruction]) -> Champion {
+    let bytes = assemble(instrs);
+    Champion {
+        name: name.to_string(),
+        comment: String::new(),
+        program: Program::new(bytes.len(), &mut bytes.as_slice()).unwrap(),
+    }
+}
+
+/// A champion named `raw` running the bytes, valid instructions or not.
+pub fn raw_champion(bytes: &[u8]) -> Champion {
+    Champion {
+        name: "raw".to_string(),
+        comment: String::new(),
+        program: Program::new(bytes.len(), &mut &bytes[..]).unwrap(),
+    }
+}
@@ -0,0 +1,42 @@
+use std::ops;
+
+/// What the machine traces, the levels are the ones of the reference VM `-v` flag
+/// and can be combined, the lines are written in the reference format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Verbosity(u8);
+
+impl Verbosity {
+    pub const ESSENTIALS: Verbosity = Verbosity(0);
+    pub const LIVES: Verbosity = Verbosity(1);
+    pub const CYCLES: Verbosity = Verbosity(2);
+    /// The operations with their arguments, values are resolved.
+    pub const OPERATIONS: Verbosity = Verbosity(4);
+    /// The processes killed, with the cycles to die.
+    pub const DEATHS: Verbosity = Verbosity(8);
+    /// The bytes skipped by each operation, successful jumps excepted.
+    pub const PC_MOVEMENTS: Verbosity = Verbosity(16);
+
+    const ALL: u8 = 31;
+
+    /// Unknown levels are ignored.
+    pub fn from_bits(bits: u8) -> Self {
+        Verbosity(bits & Verbosity::ALL)
+    }
+
+    pub fn bits(self) -> u8 {
+        self.0
+    }
+
+    /// Returns `true` if every level of `other` is enabled.
+    pub fn contains(self, other: Verbosity) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl ops::BitOr for Verbosity {
+    type Output = Verbosity;
+
+    fn bitor(self, other: Verbosity) -> Verbosity {
+        Verbosity(self.0 | other.0)
+    }
+}